@@ -0,0 +1,179 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::error::Error;
+use crate::http::Validators;
+use crate::time::now;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const CACHE_DIR: &str = "milcheck";
+
+/// A cached response body
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    url: String,
+    /// Unix time of the fetch, in seconds
+    fetched: u64,
+    pub body: String,
+    #[serde(default)]
+    pub validators: Validators,
+}
+
+/// On-disk cache of the fetched pages, under `$XDG_CACHE_HOME/milcheck/`
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: Option<PathBuf>,
+    /// Time in seconds after which an entry is stale
+    ttl: u64,
+}
+
+impl Cache {
+    pub fn new(ttl: u64) -> Self {
+        let dir = env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|dir| dir.join(CACHE_DIR));
+        Cache::with_dir(dir, ttl)
+    }
+
+    /// A cache in `dir`, none when `None`
+    pub fn with_dir(dir: Option<PathBuf>, ttl: u64) -> Self {
+        Cache { dir, ttl }
+    }
+
+    fn path(&self, url: &str) -> Option<PathBuf> {
+        let name: String = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+                _ => '_',
+            })
+            .collect();
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", name.trim_matches('_'))))
+    }
+
+    /// Get the cached entry of `url`, fresh or not. The file name is
+    /// lossy, e.g. `http://` and `https://` urls share it, hence the url
+    /// check.
+    pub fn get(&self, url: &str) -> Option<Entry> {
+        let content = fs::read_to_string(self.path(url)?).ok()?;
+        serde_json::from_str(&content)
+            .ok()
+            .filter(|entry: &Entry| entry.url == url)
+    }
+
+    pub fn is_fresh(&self, entry: &Entry) -> bool {
+        self.is_fresh_at(entry, now())
+    }
+
+    /// Whether the entry is fresh at the Unix time `time`, in seconds
+    fn is_fresh_at(&self, entry: &Entry, time: u64) -> bool {
+        time.saturating_sub(entry.fetched) < self.ttl
+    }
+
+    pub fn store(&self, url: &str, body: &str, validators: &Validators) -> Result<(), Error> {
+        let path = match self.path(url) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let entry = Entry {
+            url: url.to_string(),
+            fetched: now(),
+            body: body.to_string(),
+            validators: validators.clone(),
+        };
+        let content = serde_json::to_string(&entry)
+            .map_err(|err| Error::new(format!("cache serialization failed: {}", err)))?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    /// A cache in a fresh directory
+    fn cache(name: &str, ttl: u64) -> Cache {
+        let dir = env::temp_dir().join(format!("milcheck-cache-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Cache::with_dir(Some(dir), ttl)
+    }
+
+    fn entry(fetched: u64) -> Entry {
+        Entry {
+            url: "https://a/".to_string(),
+            fetched,
+            body: String::new(),
+            validators: Validators::default(),
+        }
+    }
+
+    #[test]
+    fn get_the_stored_entry() {
+        let cache = cache("get", 300);
+        let validators = Validators {
+            etag: Some("\"1\"".to_string()),
+            last_modified: None,
+        };
+        cache.store("https://a/json/", "body", &validators).unwrap();
+        let entry = cache.get("https://a/json/").unwrap();
+        assert_eq!(entry.body, "body");
+        assert_eq!(entry.validators.etag.as_deref(), Some("\"1\""));
+        assert!(cache.is_fresh(&entry));
+        assert!(cache.get("https://b/json/").is_none());
+        fs::remove_dir_all(cache.dir.unwrap()).unwrap();
+    }
+
+    #[test]
+    fn urls_sharing_a_file_name_do_not_share_their_entry() {
+        let cache = cache("shared", 300);
+        cache
+            .store("https://a/json/", "secure", &Validators::default())
+            .unwrap();
+        assert_eq!(cache.path("http://a/json/"), cache.path("https://a/json/"));
+        assert_eq!(cache.path("https://a/json"), cache.path("https://a/json/"));
+        assert!(cache.get("http://a/json/").is_none());
+        assert!(cache.get("https://a/json").is_none());
+        cache
+            .store("http://a/json/", "plain", &Validators::default())
+            .unwrap();
+        assert_eq!(cache.get("http://a/json/").unwrap().body, "plain");
+        assert!(cache.get("https://a/json/").is_none());
+        fs::remove_dir_all(cache.dir.unwrap()).unwrap();
+    }
+
+    #[test]
+    fn freshness_ends_with_the_ttl() {
+        let cache = Cache::with_dir(None, 300);
+        let fetched = entry(1000);
+        assert!(cache.is_fresh_at(&fetched, 1000));
+        assert!(cache.is_fresh_at(&fetched, 1299));
+        assert!(!cache.is_fresh_at(&fetched, 1300));
+        // a clock set back
+        assert!(cache.is_fresh_at(&fetched, 900));
+        assert!(!Cache::with_dir(None, 0).is_fresh_at(&fetched, 1000));
+    }
+
+    #[test]
+    fn no_directory_no_cache() {
+        let cache = Cache::with_dir(None, 300);
+        cache
+            .store("https://a/", "body", &Validators::default())
+            .unwrap();
+        assert!(cache.get("https://a/").is_none());
+    }
+}
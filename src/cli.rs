@@ -1,4 +1,6 @@
-use clap::Parser;
+use crate::table::Column;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
 
 /// Print your mirrorlist status and Arch Linux latest news
 #[derive(Parser, Debug)]
@@ -10,4 +12,191 @@ pub struct Cli {
     /// Print the latest news
     #[arg(short, long)]
     pub news: Option<u8>,
+    /// Output format of the mirrorlist status and of the lint
+    /// [default: table]
+    #[arg(long, value_enum, global = true)]
+    pub format: Option<Format>,
+    /// Also print the last sync age, the active, ISOs, IPv4 and IPv6
+    /// flags and the details link of the mirrors
+    #[arg(short, long)]
+    pub wide: bool,
+    /// Comma separated columns to print, in that order, e.g.
+    /// `state,url,delay,score,last_sync`
+    #[arg(long, value_delimiter = ',', conflicts_with = "wide")]
+    pub columns: Option<Vec<Column>>,
+    /// Order of the mirrors, worst first [default: order]
+    #[arg(long, value_enum, value_name = "KEY")]
+    pub sort: Option<Sort>,
+    /// Only print the mirrors in these states (comma separated)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "STATE")]
+    pub only: Option<Vec<StateFilter>>,
+    /// Only print the mirrors of these countries, by code or name
+    /// (comma separated), e.g. `DE,France`
+    #[arg(long, value_delimiter = ',', value_name = "COUNTRY")]
+    pub country: Option<Vec<String>>,
+    /// Only print the mirrors using these protocols (comma separated)
+    #[arg(long, value_delimiter = ',', value_name = "PROTOCOL")]
+    pub protocol: Option<Vec<String>>,
+    /// Also check the commented-out servers, e.g. `#Server = ...`, and
+    /// print them in a separate table
+    #[arg(long)]
+    pub disabled: bool,
+    /// Number of synced mirrors of the same country and protocol
+    /// suggested to replace an out of sync or unknown one, 0 to disable
+    /// [default: 3]
+    #[arg(long, value_name = "N")]
+    pub suggestions: Option<usize>,
+    /// When to use colors, `auto` disables them when the output is not
+    /// a terminal or when `NO_COLOR` is set. Without colors, the values
+    /// to watch are followed by `~` and the critical ones by `!`
+    /// [default: auto]
+    #[arg(long, value_enum, value_name = "WHEN", global = true)]
+    pub color: Option<ColorChoice>,
+    /// Exit with a code reflecting the mirrorlist status:
+    /// 0 all synced, 2 some out of sync, 3 some not found,
+    /// 4 none synced, 5 some critical values, 1 runtime or usage error
+    #[arg(short, long)]
+    pub check: bool,
+    /// Mirrorlist file to check, `-` reads from stdin (repeatable)
+    /// [default: /etc/pacman.d/mirrorlist]
+    #[arg(short = 'f', long = "file", value_name = "PATH")]
+    pub files: Vec<String>,
+    /// Check the servers of every repository of pacman.conf, following
+    /// `Include` directives [default: /etc/pacman.conf]
+    #[arg(short, long, value_name = "PATH", conflicts_with = "files")]
+    pub pacman_conf: Option<Option<String>>,
+    /// Completion percentage under which a mirror is worth a warning
+    /// [default: 100]
+    #[arg(long, value_name = "PERCENT", global = true)]
+    pub completion_warning: Option<f64>,
+    /// Completion percentage under which a mirror is critical
+    /// [default: 95]
+    #[arg(long, value_name = "PERCENT", global = true)]
+    pub completion_critical: Option<f64>,
+    /// Delay in minutes over which a mirror is worth a warning
+    /// [default: 30]
+    #[arg(long, value_name = "MINUTES", global = true)]
+    pub delay_warning: Option<u32>,
+    /// Delay in minutes over which a mirror is critical [default: 60]
+    #[arg(long, value_name = "MINUTES", global = true)]
+    pub delay_critical: Option<u32>,
+    /// Score over which a mirror is worth a warning [default: 1]
+    #[arg(long, value_name = "SCORE", global = true)]
+    pub score_warning: Option<f64>,
+    /// Score over which a mirror is critical [default: 2]
+    #[arg(long, value_name = "SCORE", global = true)]
+    pub score_critical: Option<f64>,
+    /// Time in seconds during which the fetched data are served from the
+    /// cache, 0 to always fetch [default: 300]
+    #[arg(long, value_name = "SECONDS", global = true)]
+    pub cache_ttl: Option<u64>,
+    /// Do not use the network, print the data from the cache whatever
+    /// their age
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Time limit in seconds of a request [default: 30]
+    #[arg(long, value_name = "SECONDS", global = true)]
+    pub timeout: Option<u64>,
+    /// Time limit in seconds to connect to a server [default: 10]
+    #[arg(long, value_name = "SECONDS", global = true)]
+    pub connect_timeout: Option<u64>,
+    /// Number of retries of a failed request [default: 2]
+    #[arg(long, value_name = "N", global = true)]
+    pub retries: Option<u32>,
+    /// Delay in milliseconds before the first retry, doubled on every
+    /// retry [default: 500]
+    #[arg(long, value_name = "MILLISECONDS", global = true)]
+    pub retry_delay: Option<u64>,
+    /// Url of the mirror status JSON API
+    /// [default: https://www.archlinux.org/mirrors/status/json/]
+    #[arg(long, value_name = "URL", env = "MILCHECK_STATUS_URL", global = true)]
+    pub status_url: Option<String>,
+    /// Url of the Arch Linux homepage, where the news are read from
+    /// [default: https://archlinux.org]
+    #[arg(long, value_name = "URL", env = "MILCHECK_ARCHLINUX_URL")]
+    pub archlinux_url: Option<String>,
+    /// Configuration file [default: $XDG_CONFIG_HOME/milcheck/config.toml]
+    #[arg(long, value_name = "PATH", env = "MILCHECK_CONFIG", global = true)]
+    pub config: Option<String>,
+    /// Profile of the configuration file to use
+    #[arg(long, value_name = "NAME", env = "MILCHECK_PROFILE", global = true)]
+    pub profile: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Validate a mirrorlist
+    ///
+    /// Report the syntax problems, duplicates, unsupported protocols,
+    /// plain http mirrors, missing `$repo` and `$arch` placeholders,
+    /// servers unknown to the status list and order problems. Exit with
+    /// 0 when nothing is found, 2 on warnings, 3 on errors, 1 on runtime
+    /// or usage error
+    Lint {
+        /// Mirrorlist file, `-` reads from stdin
+        /// [default: /etc/pacman.d/mirrorlist]
+        path: Option<String>,
+    },
+    /// Comment out the out of sync servers of a mirrorlist
+    ///
+    /// A `# milcheck: ...` note explaining why is written above every
+    /// commented-out server, the other lines are kept as is. The file is
+    /// backed up first, e.g. to `mirrorlist.20230620T100123.bak`. A
+    /// mirrorlist is never left without a server
+    Fix {
+        /// Mirrorlist file, `-` reads from stdin with `--dry-run`
+        /// [default: /etc/pacman.d/mirrorlist]
+        path: Option<String>,
+        /// Also comment out the servers not found in the mirror status
+        /// list
+        #[arg(long)]
+        not_found: bool,
+        /// Print the changes as a unified diff instead of writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Human readable table
+    Table,
+    /// JSON document, see the README for its schema
+    Json,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    /// Highest score first
+    Score,
+    /// Longest delay first
+    Delay,
+    /// Lowest completion first
+    Completion,
+    /// Alphabetical order of the country
+    Country,
+    /// Out of sync, then not found, then synced mirrors
+    State,
+    /// Order of the mirrorlist
+    Order,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StateFilter {
+    Synced,
+    OutOfSync,
+    NotFound,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
 }
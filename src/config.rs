@@ -0,0 +1,295 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::cli::{ColorChoice, Format, Sort, StateFilter};
+use crate::error::Error;
+use crate::table::Column;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONFIG_DIR: &str = "milcheck";
+const CONFIG_FILE: &str = "config.toml";
+
+/// User configuration, every value is optional and the command line
+/// takes precedence over it
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub mirrorlist: Option<bool>,
+    pub news: Option<u8>,
+    pub format: Option<Format>,
+    pub color: Option<ColorChoice>,
+    pub wide: Option<bool>,
+    pub columns: Option<Vec<Column>>,
+    pub sort: Option<Sort>,
+    pub suggestions: Option<usize>,
+    pub disabled: Option<bool>,
+    pub filters: Filters,
+    pub paths: Paths,
+    pub endpoints: Endpoints,
+    pub network: Network,
+    pub thresholds: Thresholds,
+    /// Named sets of values overriding the ones above
+    profile: BTreeMap<String, Config>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Filters {
+    pub only: Option<Vec<StateFilter>>,
+    pub country: Option<Vec<String>>,
+    pub protocol: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Paths {
+    pub files: Option<Vec<String>>,
+    pub pacman_conf: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Endpoints {
+    pub status: Option<String>,
+    pub archlinux: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Network {
+    pub timeout: Option<u64>,
+    pub connect_timeout: Option<u64>,
+    pub retries: Option<u32>,
+    pub retry_delay: Option<u64>,
+    pub cache_ttl: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Thresholds {
+    pub completion_warning: Option<f64>,
+    pub completion_critical: Option<f64>,
+    pub delay_warning: Option<u32>,
+    pub delay_critical: Option<u32>,
+    pub score_warning: Option<f64>,
+    pub score_critical: Option<f64>,
+}
+
+impl Config {
+    /// Load the configuration file at `path`, or the user one
+    /// `$XDG_CONFIG_HOME/milcheck/config.toml` if it exists, and apply
+    /// the given profile
+    pub fn load(path: Option<&str>, profile: Option<&str>) -> Result<Config, Error> {
+        let config = match path {
+            Some(path) => Config::read(Path::new(path))?,
+            None => match default_path() {
+                Some(path) if path.exists() => Config::read(&path)?,
+                _ => Config::default(),
+            },
+        };
+        match profile {
+            Some(name) => config.with_profile(name),
+            None => Ok(config),
+        }
+    }
+
+    fn read(path: &Path) -> Result<Config, Error> {
+        let content = fs::read_to_string(path).map_err(|err| {
+            format!(
+                "an error occured while reading the file {}: {}",
+                path.display(),
+                err
+            )
+        })?;
+        toml::from_str(&content).map_err(|err| {
+            Error::new(format!(
+                "invalid configuration file {}: {}",
+                path.display(),
+                err
+            ))
+        })
+    }
+
+    fn with_profile(mut self, name: &str) -> Result<Config, Error> {
+        let profile = self
+            .profile
+            .remove(name)
+            .ok_or_else(|| format!("no profile \"{}\" in the configuration", name))?;
+        if !profile.profile.is_empty() {
+            return Err(Error::new(format!(
+                "the profile \"{}\" cannot contain profiles",
+                name
+            )));
+        }
+        Ok(Config {
+            mirrorlist: profile.mirrorlist.or(self.mirrorlist),
+            news: profile.news.or(self.news),
+            format: profile.format.or(self.format),
+            color: profile.color.or(self.color),
+            wide: profile.wide.or(self.wide),
+            columns: profile.columns.or(self.columns),
+            sort: profile.sort.or(self.sort),
+            suggestions: profile.suggestions.or(self.suggestions),
+            disabled: profile.disabled.or(self.disabled),
+            filters: Filters {
+                only: profile.filters.only.or(self.filters.only),
+                country: profile.filters.country.or(self.filters.country),
+                protocol: profile.filters.protocol.or(self.filters.protocol),
+            },
+            paths: Paths {
+                files: profile.paths.files.or(self.paths.files),
+                pacman_conf: profile.paths.pacman_conf.or(self.paths.pacman_conf),
+            },
+            endpoints: Endpoints {
+                status: profile.endpoints.status.or(self.endpoints.status),
+                archlinux: profile.endpoints.archlinux.or(self.endpoints.archlinux),
+            },
+            network: Network {
+                timeout: profile.network.timeout.or(self.network.timeout),
+                connect_timeout: profile
+                    .network
+                    .connect_timeout
+                    .or(self.network.connect_timeout),
+                retries: profile.network.retries.or(self.network.retries),
+                retry_delay: profile.network.retry_delay.or(self.network.retry_delay),
+                cache_ttl: profile.network.cache_ttl.or(self.network.cache_ttl),
+            },
+            thresholds: Thresholds {
+                completion_warning: profile
+                    .thresholds
+                    .completion_warning
+                    .or(self.thresholds.completion_warning),
+                completion_critical: profile
+                    .thresholds
+                    .completion_critical
+                    .or(self.thresholds.completion_critical),
+                delay_warning: profile
+                    .thresholds
+                    .delay_warning
+                    .or(self.thresholds.delay_warning),
+                delay_critical: profile
+                    .thresholds
+                    .delay_critical
+                    .or(self.thresholds.delay_critical),
+                score_warning: profile
+                    .thresholds
+                    .score_warning
+                    .or(self.thresholds.score_warning),
+                score_critical: profile
+                    .thresholds
+                    .score_critical
+                    .or(self.thresholds.score_critical),
+            },
+            profile: BTreeMap::new(),
+        })
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every value, set to the `first` or the second set of values,
+    /// under the `table` if given
+    fn values(table: Option<&str>, first: bool) -> String {
+        let (yes, number, word) = if first {
+            ("true", 1, "a")
+        } else {
+            ("false", 2, "b")
+        };
+        let (format, color, sort, column, state) = if first {
+            ("table", "always", "score", "url", "synced")
+        } else {
+            ("json", "never", "delay", "score", "out-of-sync")
+        };
+        let header = |name: &str| match table {
+            Some(table) => format!("[{}.{}]", table, name),
+            None => format!("[{}]", name),
+        };
+        format!(
+            "{}mirrorlist = {yes}\nnews = {number}\nformat = \"{format}\"\ncolor = \"{color}\"\n\
+             wide = {yes}\ncolumns = [\"{column}\"]\nsort = \"{sort}\"\nsuggestions = {number}\n\
+             disabled = {yes}\n\
+             {}\nonly = [\"{state}\"]\ncountry = [\"{word}\"]\nprotocol = [\"{word}\"]\n\
+             {}\nfiles = [\"{word}\"]\npacman-conf = \"{word}\"\n\
+             {}\nstatus = \"{word}\"\narchlinux = \"{word}\"\n\
+             {}\ntimeout = {number}\nconnect-timeout = {number}\nretries = {number}\n\
+             retry-delay = {number}\ncache-ttl = {number}\n\
+             {}\ncompletion-warning = {number}.0\ncompletion-critical = {number}.0\n\
+             delay-warning = {number}\ndelay-critical = {number}\n\
+             score-warning = {number}.0\nscore-critical = {number}.0\n",
+            table
+                .map(|table| format!("[{}]\n", table))
+                .unwrap_or_default(),
+            header("filters"),
+            header("paths"),
+            header("endpoints"),
+            header("network"),
+            header("thresholds"),
+        )
+    }
+
+    fn parse(content: &str) -> Config {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn profile_overrides_every_value() {
+        let content = values(None, true) + &values(Some("profile.x"), false);
+        let config = parse(&content).with_profile("x").unwrap();
+        // a value missed by the merge would be the one of the base
+        assert_eq!(
+            format!("{:?}", config),
+            format!("{:?}", parse(&values(None, false)))
+        );
+    }
+
+    #[test]
+    fn profile_keeps_the_other_values() {
+        let config = parse(
+            "news = 3\nsort = \"score\"\n[network]\nretries = 4\ntimeout = 5\n\
+             [profile.x]\nnews = 1\n[profile.x.network]\nretries = 0\n",
+        )
+        .with_profile("x")
+        .unwrap();
+        assert_eq!(config.news, Some(1));
+        assert_eq!(config.sort, Some(Sort::Score));
+        assert_eq!(config.network.retries, Some(0));
+        assert_eq!(config.network.timeout, Some(5));
+        assert!(config.profile.is_empty());
+    }
+
+    #[test]
+    fn unknown_profile() {
+        let err = parse("[profile.x]\nnews = 1\n")
+            .with_profile("y")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no profile \"y\" in the configuration");
+    }
+
+    #[test]
+    fn nested_profiles_are_rejected() {
+        let err = parse("[profile.x.profile.y]\nnews = 1\n")
+            .with_profile("x")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "the profile \"x\" cannot contain profiles");
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("[profile.x]\nnewz = 1\n").is_err());
+    }
+}
@@ -5,12 +5,12 @@
 use crate::error::Error;
 use std::io;
 use std::sync::mpsc::{self, Receiver, RecvError, TryRecvError::Disconnected};
-use std::thread::{self, JoinHandle};
+use std::thread;
 use std::time::Duration;
 use termion::event::Key;
 use termion::input::TermRead;
 
-const TICK_RATE: Duration = Duration::from_millis(16);
+pub const TICK_RATE: Duration = Duration::from_millis(16);
 const EXIT_KEYS: [Key; 3] = [Key::Char('q'), Key::Esc, Key::Ctrl('c')];
 
 pub enum Event<I> {
@@ -18,21 +18,22 @@ pub enum Event<I> {
     Tick,
 }
 
+/// The key and tick events, the threads sending them are detached: they
+/// end with the first exit key, or at their next event once `Events` is
+/// dropped
 pub struct Events {
     rx: Receiver<Event<Key>>,
-    input_handle: JoinHandle<Result<(), Error>>,
-    tick_handle: JoinHandle<Result<(), Error>>,
 }
 
 impl Events {
     pub fn is_exit_key(key: Key) -> bool {
-        EXIT_KEYS.iter().any(|&k| k == key)
+        EXIT_KEYS.contains(&key)
     }
 
     pub fn new() -> Events {
         let (tx, rx) = mpsc::channel();
         let (tick_tx, tick_rx) = mpsc::channel();
-        let input_handle = {
+        {
             let tx = tx.clone();
             let tick_tx = tick_tx;
             thread::spawn(move || -> Result<(), Error> {
@@ -40,15 +41,15 @@ impl Events {
                 for input in stdin.keys() {
                     let key = input?;
                     tx.send(Event::Input(key))?;
-                    if EXIT_KEYS.iter().any(|&k| k == key) {
+                    if EXIT_KEYS.contains(&key) {
                         tick_tx.send(())?;
                         return Ok(());
                     }
                 }
                 Ok(())
-            })
-        };
-        let tick_handle = {
+            });
+        }
+        {
             let tx = tx;
             thread::spawn(move || -> Result<(), Error> {
                 loop {
@@ -63,22 +64,12 @@ impl Events {
                     }
                     thread::sleep(TICK_RATE);
                 }
-            })
-        };
-        Events {
-            rx,
-            input_handle,
-            tick_handle,
+            });
         }
+        Events { rx }
     }
 
     pub fn next(&self) -> Result<Event<Key>, RecvError> {
         self.rx.recv()
     }
-
-    pub fn finish(self) -> Result<(), Error> {
-        self.input_handle.join().unwrap()?;
-        self.tick_handle.join().unwrap()?;
-        Ok(())
-    }
 }
@@ -0,0 +1,277 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::mirrorlist::Entry;
+use crate::time::{format_iso8601, format_stamp};
+use crate::MirrorState;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::process;
+
+/// Number of unchanged lines around the changes of the diff
+const CONTEXT: usize = 3;
+const NOTE_PREFIX: &str = "# milcheck: ";
+
+/// A server line to comment out, with the reason written above it
+#[derive(Debug, Clone)]
+pub struct Change {
+    /// Line number, from 1
+    pub line: usize,
+    pub reason: String,
+}
+
+/// The out of sync servers to comment out, and the ones not found in
+/// the status list with `not_found`
+pub fn changes(servers: &[(&Entry, MirrorState)], not_found: bool) -> Vec<Change> {
+    servers
+        .iter()
+        .filter_map(|(entry, state)| {
+            let reason = match state {
+                MirrorState::Synced(_) => return None,
+                MirrorState::OutOfSync(mirror) if !mirror.active => {
+                    "inactive in the mirror status list".to_string()
+                }
+                MirrorState::OutOfSync(mirror) => match mirror.last_sync {
+                    Some(last_sync) => format!("out of sync since {}", format_iso8601(last_sync)),
+                    None => "out of sync, never synced".to_string(),
+                },
+                MirrorState::NotFound(_) if not_found => {
+                    "not found in the mirror status list".to_string()
+                }
+                MirrorState::NotFound(_) => return None,
+            };
+            Some(Change {
+                line: entry.line,
+                reason,
+            })
+        })
+        .collect()
+}
+
+/// The lines of a content with their line ending, so that joining them
+/// gives the content back byte for byte
+fn lines(content: &str) -> Vec<&str> {
+    content.split_inclusive('\n').collect()
+}
+
+/// The note and the commented-out line replacing a line, with the line
+/// ending of the line
+fn rewrite(line: &str, change: &Change) -> [String; 2] {
+    let ending = if line.ends_with("\r\n") { "\r\n" } else { "\n" };
+    [
+        format!("{}{}{}", NOTE_PREFIX, change.reason, ending),
+        format!("#{}", line),
+    ]
+}
+
+fn change_of(changes: &[Change], index: usize) -> Option<&Change> {
+    changes.iter().find(|change| change.line == index + 1)
+}
+
+/// Comment out the changed lines, the other ones are kept as is
+pub fn apply(content: &str, changes: &[Change]) -> String {
+    let mut fixed = String::with_capacity(content.len());
+    for (index, line) in lines(content).into_iter().enumerate() {
+        match change_of(changes, index) {
+            Some(change) => fixed.extend(rewrite(line, change)),
+            None => fixed.push_str(line),
+        }
+    }
+    fixed
+}
+
+/// Unified diff of the changes, as `diff -u` prints it
+pub fn diff(path: &str, content: &str, changes: &[Change]) -> String {
+    let lines = lines(content);
+    let mut indexes: Vec<usize> = changes.iter().map(|change| change.line - 1).collect();
+    indexes.sort_unstable();
+    // the changes with overlapping contexts share a hunk
+    let mut hunks: Vec<Vec<usize>> = vec![];
+    for index in indexes {
+        match hunks.last_mut() {
+            Some(hunk) if index - hunk[hunk.len() - 1] <= 2 * CONTEXT + 1 => hunk.push(index),
+            _ => hunks.push(vec![index]),
+        }
+    }
+    let mut diff = format!("--- {}\n+++ {}\n", path, path);
+    // every change adds a note line
+    let mut added = 0;
+    for hunk in hunks {
+        let start = hunk[0].saturating_sub(CONTEXT);
+        let end = (hunk[hunk.len() - 1] + CONTEXT + 1).min(lines.len());
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            start + 1,
+            end - start,
+            start + added + 1,
+            end - start + hunk.len()
+        ));
+        for (index, line) in lines.iter().enumerate().take(end).skip(start) {
+            match change_of(changes, index) {
+                Some(change) => {
+                    push_line(&mut diff, '-', line);
+                    for line in rewrite(line, change) {
+                        push_line(&mut diff, '+', &line);
+                    }
+                }
+                None => push_line(&mut diff, ' ', line),
+            }
+        }
+        added += hunk.len();
+    }
+    diff
+}
+
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line.strip_suffix('\n').unwrap_or(line));
+    diff.push('\n');
+    if !line.ends_with('\n') {
+        diff.push_str("\\ No newline at end of file\n");
+    }
+}
+
+/// Back up the content of a mirrorlist next to it, e.g. to
+/// `mirrorlist.20230620T100123.bak`, an existing backup is never
+/// overwritten: a counter is added to the name instead. The permissions
+/// of the mirrorlist are kept.
+pub fn backup(path: &str, content: &str, time: u64) -> Result<String, String> {
+    let error = |err: io::Error| format!("an error occured while backing up {}: {}", path, err);
+    let permissions = fs::metadata(path).map_err(error)?.permissions();
+    let stamp = format_stamp(time);
+    let mut counter = 0;
+    loop {
+        let backup = match counter {
+            0 => format!("{}.{}.bak", path, stamp),
+            _ => format!("{}.{}.{}.bak", path, stamp, counter),
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&backup)
+        {
+            Ok(mut file) => {
+                return file
+                    .set_permissions(permissions)
+                    .and_then(|_| write_all(&mut file, content))
+                    .map(|_| backup)
+                    .map_err(error)
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => counter += 1,
+            Err(err) => return Err(error(err)),
+        }
+    }
+}
+
+/// Replace the content of a file through a temporary file of the same
+/// directory renamed over it, so that a failed write leaves the file
+/// untouched. The permissions of the file are kept.
+pub fn write_atomically(path: &str, content: &str) -> Result<(), String> {
+    let error =
+        |err: io::Error| format!("an error occured while writing the file {}: {}", path, err);
+    let permissions = fs::metadata(path).map_err(error)?.permissions();
+    let temporary = format!("{}.milcheck-{}.tmp", path, process::id());
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temporary)
+        .and_then(|mut file| {
+            file.set_permissions(permissions)?;
+            write_all(&mut file, content)
+        })
+        .and_then(|_| fs::rename(&temporary, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result.map_err(error)
+}
+
+fn write_all(file: &mut File, content: &str) -> io::Result<()> {
+    file.write_all(content.as_bytes())?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(line: usize) -> Change {
+        Change {
+            line,
+            reason: "out of sync".to_string(),
+        }
+    }
+
+    fn numbered_lines(count: usize) -> String {
+        (1..=count).map(|n| format!("line {}\n", n)).collect()
+    }
+
+    fn headers(diff: &str) -> Vec<&str> {
+        diff.lines().filter(|line| line.starts_with("@@")).collect()
+    }
+
+    #[test]
+    fn close_changes_share_a_hunk() {
+        // 6 unchanged lines between the changes, both contexts overlap
+        let diff = diff("ml", &numbered_lines(20), &[change(2), change(9)]);
+        assert_eq!(headers(&diff), ["@@ -1,12 +1,14 @@"]);
+    }
+
+    #[test]
+    fn distant_changes_get_their_own_hunk() {
+        // 7 unchanged lines between the changes
+        let diff = diff("ml", &numbered_lines(20), &[change(2), change(10)]);
+        assert_eq!(headers(&diff), ["@@ -1,5 +1,6 @@", "@@ -7,7 +8,8 @@"]);
+    }
+
+    #[test]
+    fn diff_of_a_change() {
+        let diff = diff("ml", &numbered_lines(10), &[change(5)]);
+        assert_eq!(
+            diff,
+            "--- ml\n+++ ml\n@@ -2,7 +2,8 @@\n line 2\n line 3\n line 4\n-line 5\n\
+             +# milcheck: out of sync\n+#line 5\n line 6\n line 7\n line 8\n"
+        );
+    }
+
+    #[test]
+    fn change_of_a_last_line_without_newline() {
+        let content = "a\nServer = x";
+        assert_eq!(
+            diff("ml", content, &[change(2)]),
+            "--- ml\n+++ ml\n@@ -1,2 +1,3 @@\n a\n-Server = x\n\
+             \\ No newline at end of file\n+# milcheck: out of sync\n+#Server = x\n\
+             \\ No newline at end of file\n"
+        );
+        assert_eq!(
+            apply(content, &[change(2)]),
+            "a\n# milcheck: out of sync\n#Server = x"
+        );
+    }
+
+    #[test]
+    fn crlf_endings_are_kept() {
+        let content = "a\r\nServer = x\r\nb\r\n";
+        assert_eq!(
+            apply(content, &[change(2)]),
+            "a\r\n# milcheck: out of sync\r\n#Server = x\r\nb\r\n"
+        );
+        assert_eq!(
+            diff("ml", content, &[change(2)]),
+            "--- ml\n+++ ml\n@@ -1,3 +1,4 @@\n a\r\n-Server = x\r\n\
+             +# milcheck: out of sync\r\n+#Server = x\r\n b\r\n"
+        );
+    }
+
+    #[test]
+    fn apply_keeps_the_other_lines() {
+        let content = "## head \t\n\nServer=x # comment\r\n  Server = y\n\n#Server = z\nend";
+        assert_eq!(apply(content, &[]), content);
+        assert_eq!(
+            apply(content, &[change(4)]),
+            "## head \t\n\nServer=x # comment\r\n# milcheck: out of sync\n#  Server = y\n\n\
+             #Server = z\nend"
+        );
+    }
+}
@@ -3,30 +3,134 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use crate::error::Error;
-use reqwest::blocking;
+use reqwest::blocking::{Client, Response as HttpResponse};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use std::str;
 use std::sync::mpsc::{self, Receiver};
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-pub struct Http(JoinHandle<Result<(), Error>>, Receiver<String>);
+const USER_AGENT: &str = concat!("milcheck/", env!("CARGO_PKG_VERSION"));
+
+/// Network settings shared by all the requests
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Time limit of a whole request, from connection to body
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Number of attempts after the first failed one
+    pub retries: u32,
+    /// Delay before the first retry, doubled on every retry
+    pub retry_delay: Duration,
+}
+
+/// Cache validators of a response, sent back to make the request
+/// conditional
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl From<&HeaderMap> for Validators {
+    fn from(headers: &HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        Validators {
+            etag: get(ETAG),
+            last_modified: get(LAST_MODIFIED),
+        }
+    }
+}
+
+pub enum Response {
+    Body(String, Validators),
+    /// The content did not change since the given validators
+    NotModified,
+}
+
+/// Build the client shared by all the requests
+pub fn client(settings: &Settings) -> Result<Client, Error> {
+    Ok(Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(settings.timeout)
+        .connect_timeout(settings.connect_timeout)
+        .build()?)
+}
+
+pub struct Http(JoinHandle<Result<(), Error>>, Receiver<Response>);
 
 impl Http {
-    pub fn get(url: &str) -> Http {
+    pub fn get(client: &Client, settings: &Settings, url: &str, validators: &Validators) -> Http {
         let (tx, rx) = mpsc::channel();
-        let url_cloned = String::from(url);
+        let client = client.clone();
+        let settings = settings.clone();
+        let url = String::from(url);
+        let validators = validators.clone();
         let handle = thread::spawn(move || -> Result<(), Error> {
-            let content = blocking::get(&url_cloned)?.text()?;
-            Ok(tx.send(content)?)
+            let response = send(&client, &settings, &url, &validators)
+                .map_err(|err| format!("failed to fetch {}: {}", url, err.without_url()))?;
+            Ok(tx.send(response)?)
         });
         Http(handle, rx)
     }
 
-    pub fn wait(self) -> Result<String, Error> {
-        let mut response: String = "".to_string();
-        if let Ok(msg) = self.1.recv() {
-            response = msg;
-        }
+    pub fn wait(self) -> Result<Response, Error> {
+        let response = self.1.recv();
         self.0.join().unwrap()?;
-        Ok(response)
+        Ok(response?)
+    }
+}
+
+/// Send the request and read the body, retrying with an exponential
+/// backoff on connection failures, timeouts, interrupted bodies and
+/// server errors
+fn send(
+    client: &Client,
+    settings: &Settings,
+    url: &str,
+    validators: &Validators,
+) -> Result<Response, reqwest::Error> {
+    let mut attempt = 0;
+    loop {
+        let mut request = client.get(url);
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let result = request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(read);
+        match result {
+            Err(err) if attempt < settings.retries && is_transient(&err) => {
+                thread::sleep(settings.retry_delay * 2u32.saturating_pow(attempt));
+                attempt += 1;
+            }
+            _ => return result,
+        }
+    }
+}
+
+fn read(response: HttpResponse) -> Result<Response, reqwest::Error> {
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Response::NotModified);
     }
+    let validators = Validators::from(response.headers());
+    Ok(Response::Body(response.text()?, validators))
+}
+
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_connect()
+        || err.is_timeout()
+        || err.is_body()
+        || err.status().is_some_and(|status| status.is_server_error())
 }
@@ -2,120 +2,618 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+mod cache;
 pub mod cli;
+mod config;
 mod error;
 mod event;
+mod fix;
 mod http;
+mod lint;
+mod matching;
+mod mirrorlist;
 mod news;
+mod order;
+mod pacman;
+mod painter;
 mod render;
-use cli::Cli;
+mod report;
+mod suggest;
+pub mod table;
+mod thresholds;
+mod time;
+mod view;
+use cache::Cache;
+use cli::{Cli, ColorChoice, Command, Format, Sort};
+use config::Config;
 use error::Error;
-use http::Http;
+use http::{Http, Response, Settings as NetworkSettings};
+use lint::Finding;
+use matching::{diagnose, normalize, Diagnosis};
+use mirrorlist::{print_problems, Directive, Entry, Severity};
 use news::News;
+use order::Order;
+use pacman::parse_pacman_conf;
+use painter::{Level, Painter};
 use render::Render;
+use report::{LintReport, Report};
+use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
-use std::cmp;
+use std::env;
 use std::fs;
+use std::io::{self, Read};
 use std::str;
 use std::sync::mpsc::{self, Receiver, Sender};
-use termion::color::{Color, Fg, Green, Red, Reset as ColorReset, Yellow};
+use std::time::Duration;
+use suggest::{suggest, Suggestion};
+use table::{print_mirrors, Column, COLUMNS, WIDE_COLUMNS};
+use termion::color::{Fg, Red, Yellow};
 use termion::style::{Bold, Reset};
+use termion::{is_tty, terminal_size};
+use thresholds::{Threshold, Thresholds};
+use view::View;
 
 const PACMAN_MIRRORLIST: &str = "/etc/pacman.d/mirrorlist";
-const MIRROR_STATUS_URL: &str = "https://www.archlinux.org/mirrors/status/";
+const PACMAN_CONF: &str = "/etc/pacman.conf";
+const STDIN_PATH: &str = "-";
 const MIRROR_STATUS_JSON_URL: &str = "https://www.archlinux.org/mirrors/status/json/";
 const ARCHLINUX_ORG_URL: &str = "https://archlinux.org";
-const OUTOFSYNC_HTML_TAG: &str = "<table id=\"outofsync_mirrors\"";
-const INSYNC_HTML_TAG: &str = "<table id=\"successful_mirrors\"";
-const OK: &str = "Ok";
-const NOT_FOUND: &str = "Not found!";
-const OUT_OF_SYNC: &str = "Out of sync!";
-const HEADERS: [&str; 9] = [
-    "State", "Url", "Proto", // Protocol
-    "Country", "Comp%", // Completion
-    "Delay", // Delay (hh:mm)
-    "Avg",   // Average time (s)
-    "Dev",   // The standard deviation time (s)
-    "Score",
-];
+const CACHE_TTL: u64 = 300;
+const TIMEOUT: u64 = 30;
+const CONNECT_TIMEOUT: u64 = 10;
+const RETRIES: u32 = 2;
+const RETRY_DELAY: u64 = 500;
+const SUGGESTIONS: usize = 3;
+const NO_COLOR: &str = "NO_COLOR";
 
 #[derive(Debug, Clone)]
 pub struct Milcheck {
     print_mirrorlist: bool,
     print_news: bool,
     last: Option<u8>,
+    format: Format,
+    check: bool,
+    source: Source,
+    cache: Cache,
+    offline: bool,
+    network: NetworkSettings,
+    status_url: String,
+    archlinux_url: String,
+    painter: Painter,
+    thresholds: Thresholds,
+    columns: Vec<Column>,
+    /// Whether the columns may be dropped to fit the terminal, not
+    /// when the user chose them
+    drop_columns: bool,
+    view: View,
+    /// Number of replacements suggested for a mirror
+    suggestions: usize,
+    /// Evaluate the commented-out servers
+    disabled: bool,
+    command: Option<Command>,
 }
 
-impl From<Cli> for Milcheck {
-    fn from(cli: Cli) -> Self {
-        let mut print_mirrorlist = false;
-        let mut print_news = false;
-        let mut last: Option<u8> = None;
+/// Where the servers to check are read from
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// Mirrorlist files, `-` standing for stdin
+    Files(Vec<String>),
+    /// The repositories of a pacman.conf file
+    PacmanConf(String),
+}
 
-        // if `-m` flag is passed without value, it's considered as true
-        if let Some(None) = cli.mirrorlist {
-            print_mirrorlist = true;
-        }
-        // if its value has been set by the user, use it
-        if let Some(Some(v)) = cli.mirrorlist {
-            print_mirrorlist = v;
-        }
+impl TryFrom<Cli> for Milcheck {
+    type Error = Error;
 
-        // by default, without any flags, print mirrorlist status
-        if cli.mirrorlist.is_none() && cli.news.is_none() {
-            return Milcheck {
-                print_mirrorlist: true,
-                print_news: false,
-                last: None,
-            };
+    fn try_from(cli: Cli) -> Result<Self, Error> {
+        let config = Config::load(cli.config.as_deref(), cli.profile.as_deref())?;
+        let network = NetworkSettings {
+            timeout: Duration::from_secs(cli.timeout.or(config.network.timeout).unwrap_or(TIMEOUT)),
+            connect_timeout: Duration::from_secs(
+                cli.connect_timeout
+                    .or(config.network.connect_timeout)
+                    .unwrap_or(CONNECT_TIMEOUT),
+            ),
+            retries: cli.retries.or(config.network.retries).unwrap_or(RETRIES),
+            retry_delay: Duration::from_millis(
+                cli.retry_delay
+                    .or(config.network.retry_delay)
+                    .unwrap_or(RETRY_DELAY),
+            ),
+        };
+        let defaults = Thresholds::default();
+        let thresholds = Thresholds {
+            completion: Threshold {
+                warning: cli
+                    .completion_warning
+                    .or(config.thresholds.completion_warning)
+                    .unwrap_or(defaults.completion.warning),
+                critical: cli
+                    .completion_critical
+                    .or(config.thresholds.completion_critical)
+                    .unwrap_or(defaults.completion.critical),
+            },
+            delay: Threshold {
+                warning: cli
+                    .delay_warning
+                    .or(config.thresholds.delay_warning)
+                    .unwrap_or(defaults.delay.warning),
+                critical: cli
+                    .delay_critical
+                    .or(config.thresholds.delay_critical)
+                    .unwrap_or(defaults.delay.critical),
+            },
+            score: Threshold {
+                warning: cli
+                    .score_warning
+                    .or(config.thresholds.score_warning)
+                    .unwrap_or(defaults.score.warning),
+                critical: cli
+                    .score_critical
+                    .or(config.thresholds.score_critical)
+                    .unwrap_or(defaults.score.critical),
+            },
+        };
+        thresholds.validate()?;
+        let (columns, drop_columns) = if let Some(columns) = cli.columns {
+            (columns, false)
+        } else if cli.wide {
+            (WIDE_COLUMNS.to_vec(), true)
+        } else if let Some(columns) = config.columns {
+            (columns, false)
+        } else if config.wide.unwrap_or(false) {
+            (WIDE_COLUMNS.to_vec(), true)
+        } else {
+            (COLUMNS.to_vec(), true)
+        };
+        if columns.is_empty() {
+            return Err(Error::new("no column to print"));
         }
+        let cache_ttl = cli
+            .cache_ttl
+            .or(config.network.cache_ttl)
+            .unwrap_or(CACHE_TTL);
 
-        if let Some(n) = cli.news {
-            print_news = true;
-            last = Some(n);
-        }
+        let format = cli.format.or(config.format).unwrap_or(Format::Table);
+        // the news of the configuration file are a default printed along
+        // the mirrorlist, left out of the JSON output
+        let config_news = config.news.filter(|_| format == Format::Table);
+        let last = cli.news.or(config_news);
 
-        Milcheck {
+        let source = if !cli.files.is_empty() {
+            Source::Files(cli.files.clone())
+        } else if let Some(path) = &cli.pacman_conf {
+            Source::PacmanConf(path.as_deref().unwrap_or(PACMAN_CONF).to_string())
+        } else if let Some(files) = config.paths.files {
+            Source::Files(files)
+        } else if let Some(path) = config.paths.pacman_conf {
+            Source::PacmanConf(path)
+        } else {
+            Source::Files(vec![PACMAN_MIRRORLIST.to_string()])
+        };
+
+        // by default, without any flags, print mirrorlist status, `-n`
+        // alone only prints the news. If `-m` flag is passed without
+        // value, it's considered as true. Checking the mirrorlist or
+        // passing a source implies to print it.
+        let print_mirrorlist = if cli.check || !cli.files.is_empty() || cli.pacman_conf.is_some() {
+            true
+        } else if let Some(mirrorlist) = cli.mirrorlist {
+            mirrorlist.unwrap_or(true)
+        } else if cli.news.is_some() {
+            false
+        } else {
+            config.mirrorlist.unwrap_or(true) || last.is_none()
+        };
+
+        Ok(Milcheck {
             print_mirrorlist,
-            print_news,
+            print_news: last.is_some(),
             last,
+            format,
+            check: cli.check,
+            source,
+            cache: Cache::new(cache_ttl),
+            offline: cli.offline,
+            network,
+            status_url: cli
+                .status_url
+                .or(config.endpoints.status)
+                .unwrap_or_else(|| MIRROR_STATUS_JSON_URL.to_string()),
+            archlinux_url: cli
+                .archlinux_url
+                .or(config.endpoints.archlinux)
+                .unwrap_or_else(|| ARCHLINUX_ORG_URL.to_string()),
+            painter: Painter::new(color_enabled(
+                cli.color.or(config.color).unwrap_or(ColorChoice::Auto),
+            )),
+            thresholds,
+            columns,
+            drop_columns,
+            disabled: cli.disabled || config.disabled.unwrap_or(false),
+            suggestions: cli
+                .suggestions
+                .or(config.suggestions)
+                .unwrap_or(SUGGESTIONS),
+            command: cli.command,
+            view: View {
+                sort: cli.sort.or(config.sort).unwrap_or(Sort::Order),
+                states: cli.only.or(config.filters.only).unwrap_or_default(),
+                countries: cli.country.or(config.filters.country).unwrap_or_default(),
+                protocols: cli.protocol.or(config.filters.protocol).unwrap_or_default(),
+            },
+        })
+    }
+}
+
+/// Colors are automatically disabled when the output is not a terminal,
+/// as escape sequences would pollute a file or a pipe, and when
+/// `NO_COLOR` is set, see https://no-color.org
+fn color_enabled(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            is_tty(&io::stdout()) && env::var_os(NO_COLOR).is_none_or(|value| value.is_empty())
         }
     }
 }
 
 impl Milcheck {
-    pub fn run(&mut self) -> Result<(), Error> {
+    /// Returns the exit code when running in check mode or linting
+    pub fn run(&mut self) -> Result<Option<i32>, Error> {
+        match &self.command {
+            Some(Command::Lint { path }) => {
+                let path = path.as_deref().unwrap_or(PACMAN_MIRRORLIST);
+                return self.lint(path).map(Some);
+            }
+            Some(Command::Fix {
+                path,
+                not_found,
+                dry_run,
+            }) => {
+                let path = path.as_deref().unwrap_or(PACMAN_MIRRORLIST);
+                return self.fix(path, *not_found, *dry_run).map(|_| None);
+            }
+            None => {}
+        }
+        if self.format == Format::Json && self.print_news {
+            return Err(Error::new("the news can only be printed in table format"));
+        }
         let (tx, rx) = mpsc::channel();
-        let mut render = Render::new();
+        let mut render = Render::new(is_tty(&io::stderr()), self.painter);
         let tx_cloned = Sender::clone(&tx);
-        match logic(
-            tx_cloned,
-            rx,
-            &mut render,
-            self.print_mirrorlist,
-            self.print_news,
-            self.last,
-        ) {
+        let client = http::client(&self.network)?;
+        match logic(tx_cloned, rx, &mut render, self, &client) {
             Ok((mirrors, news)) => {
                 drop(tx);
                 render.finish()?;
-                if let Some(m) = mirrors {
-                    print_mirrors(m)?;
+                let mut status = None;
+                if let Some(lists) = mirrors {
+                    let view = self.view.apply(&lists);
+                    match self.format {
+                        Format::Table => {
+                            // a file or a pipe gets the whole values
+                            let max_width = if is_tty(&io::stdout()) {
+                                terminal_size()
+                                    .ok()
+                                    .map(|(width, _)| width as usize)
+                                    .filter(|width| *width > 0)
+                            } else {
+                                None
+                            };
+                            for (list, whole_list) in view.iter().zip(&lists) {
+                                // only label the tables when there is several of them
+                                if view.len() > 1 || list.repository.is_some() {
+                                    println!(
+                                        "{}{}{}",
+                                        self.painter.paint(Bold),
+                                        list.name(),
+                                        self.painter.paint(Reset)
+                                    );
+                                }
+                                if list.unofficial {
+                                    println!(
+                                        "unofficial repository, none of its servers is in the mirror status list\n"
+                                    );
+                                    continue;
+                                }
+                                // a list of disabled servers only has no table
+                                if list.mirrors.is_empty() && !whole_list.mirrors.is_empty() {
+                                    println!("no mirror matches the filters\n");
+                                } else if !whole_list.mirrors.is_empty() {
+                                    print_mirrors(
+                                        &list.mirrors,
+                                        &self.columns,
+                                        self.drop_columns,
+                                        &self.painter,
+                                        &self.thresholds,
+                                        max_width,
+                                    );
+                                }
+                                // pacman reads the whole list in its order
+                                let warnings =
+                                    Order::new(&whole_list.mirrors, &self.thresholds).warnings();
+                                for warning in &warnings {
+                                    println!(
+                                        "{}{}warning:{} {}",
+                                        self.painter.paint(Bold),
+                                        self.painter.paint(Fg(Yellow)),
+                                        self.painter.paint(Reset),
+                                        warning
+                                    );
+                                }
+                                if !warnings.is_empty() {
+                                    println!();
+                                }
+                                print_diagnoses(&list.diagnoses, &self.painter);
+                                print_suggestions(&list.suggestions, &self.painter);
+                                if !list.disabled.is_empty() {
+                                    println!(
+                                        "{}Disabled servers of {}{}",
+                                        self.painter.paint(Bold),
+                                        list.name(),
+                                        self.painter.paint(Reset)
+                                    );
+                                    print_mirrors(
+                                        &list.disabled,
+                                        &self.columns,
+                                        self.drop_columns,
+                                        &self.painter,
+                                        &self.thresholds,
+                                        max_width,
+                                    );
+                                }
+                            }
+                        }
+                        Format::Json => println!(
+                            "{}",
+                            Report::new(&view, &lists, &self.thresholds).to_json()?
+                        ),
+                    }
+                    // the check mode reports on all the mirrors whatever the filters
+                    if self.check {
+                        status = Status::of(&lists, &self.thresholds).map(|status| status.code());
+                    }
                 }
                 if let Some(text) = news {
                     println!("{}", text);
                 }
+                Ok(status)
             }
             Err(err) => {
                 drop(tx);
                 render.finish()?;
-                return Err(err);
+                Err(err)
             }
         }
+    }
+
+    /// Lint a mirrorlist, returns the exit code
+    fn lint(&self, path: &str) -> Result<i32, Error> {
+        let mirrorlist = mirrorlist::parse(&read_mirrorlist(path)?);
+        let client = http::client(&self.network)?;
+        let json: JsonResponse = serde_json::from_str(&fetch(&self.status_url, &client, self)?)
+            .map_err(|err| format!("json response parsing failed: {}", err))?;
+        let findings = lint::lint(&mirrorlist, &json, &self.thresholds);
+        let name = mirrorlist_name(path);
+        match self.format {
+            Format::Table => print_findings(name, &findings, &self.painter),
+            Format::Json => println!("{}", LintReport::new(path, &findings).to_json()?),
+        }
+        Ok(lint::code(&findings))
+    }
+
+    /// Comment out the bad servers of a mirrorlist, or print the diff
+    /// with `dry_run`
+    fn fix(&self, path: &str, not_found: bool, dry_run: bool) -> Result<(), Error> {
+        if path == STDIN_PATH && !dry_run {
+            return Err(Error::new("stdin can only be fixed with --dry-run"));
+        }
+        let content = read_mirrorlist(path)?;
+        let mirrorlist = mirrorlist::parse(&content);
+        let name = mirrorlist_name(path);
+        print_problems(name, &mirrorlist.problems);
+        let servers: Vec<&Entry> = mirrorlist
+            .entries
+            .iter()
+            .filter(|entry| entry.directive == Directive::Server)
+            .collect();
+        if servers.is_empty() {
+            return Err(Error::new(format!("no server found in {}", name)));
+        }
+        let client = http::client(&self.network)?;
+        let json: JsonResponse = serde_json::from_str(&fetch(&self.status_url, &client, self)?)
+            .map_err(|err| format!("json response parsing failed: {}", err))?;
+        let normalized: Vec<String> = json.urls.iter().map(|m| normalize(&m.url)).collect();
+        let states: Vec<(&Entry, MirrorState)> = servers
+            .into_iter()
+            .map(|entry| (entry, mirror_state(entry.base(), &json, &normalized)))
+            .collect();
+        let changes = fix::changes(&states, not_found);
+        if changes.is_empty() {
+            println!("{}: nothing to fix", name);
+            return Ok(());
+        }
+        if changes.len() == states.len() {
+            return Err(Error::new(format!(
+                "refusing to comment out all the servers of {}, pacman would have no mirror left",
+                name
+            )));
+        }
+        if dry_run {
+            print!("{}", fix::diff(path, &content, &changes));
+            return Ok(());
+        }
+        let backup = fix::backup(path, &content, time::now())?;
+        fix::write_atomically(path, &fix::apply(&content, &changes))?;
+        println!(
+            "{}: {} server(s) commented out, backup in {}",
+            name,
+            changes.len(),
+            backup
+        );
         Ok(())
     }
 }
 
+/// Health of the mirrorlist, reported through the exit code in check mode.
+/// Variants are ordered by severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    /// All mirrors are synced
+    Synced,
+    /// Some synced mirrors have a completion, a delay or a score beyond
+    /// the critical threshold
+    Critical,
+    /// Some mirrors are not found in the mirror status list
+    NotFound,
+    /// Some mirrors are out of sync
+    OutOfSync,
+    /// None of the mirrors is synced
+    Unusable,
+}
+
+impl Status {
+    /// The worst status of the mirrorlists, the unofficial repositories
+    /// left out, `None` when there is only those
+    fn of(lists: &[Mirrorlist], thresholds: &Thresholds) -> Option<Self> {
+        lists
+            .iter()
+            .filter(|list| !list.unofficial)
+            .map(|list| Status::new(&list.mirrors, thresholds))
+            .max()
+    }
+
+    pub fn new(mirrors: &[MirrorState], thresholds: &Thresholds) -> Self {
+        if !mirrors.iter().any(|m| matches!(m, MirrorState::Synced(_))) {
+            Status::Unusable
+        } else if mirrors
+            .iter()
+            .any(|m| matches!(m, MirrorState::OutOfSync(_)))
+        {
+            Status::OutOfSync
+        } else if mirrors
+            .iter()
+            .any(|m| matches!(m, MirrorState::NotFound(_)))
+        {
+            Status::NotFound
+        } else if mirrors.iter().any(|m| match m {
+            MirrorState::Synced(mirror) => thresholds.level(mirror) == Level::Critical,
+            _ => false,
+        }) {
+            Status::Critical
+        } else {
+            Status::Synced
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        match self {
+            Status::Synced => 0,
+            Status::OutOfSync => 2,
+            Status::NotFound => 3,
+            Status::Unusable => 4,
+            Status::Critical => 5,
+        }
+    }
+}
+
+/// The status of the mirrors of a mirrorlist file, or of a pacman.conf
+/// repository
+#[derive(Debug, Clone)]
+pub struct Mirrorlist {
+    path: String,
+    repository: Option<String>,
+    mirrors: Vec<MirrorState>,
+    /// Replacements of its out of sync and unknown mirrors
+    suggestions: Vec<Suggestion>,
+    /// Why its unknown mirrors are not found
+    diagnoses: Vec<Diagnosis>,
+    /// Its commented-out mirrors, when evaluated
+    disabled: Vec<MirrorState>,
+    /// A pacman.conf repository none of whose servers is in the status
+    /// list, e.g. a third-party or a local one, left out of the checks
+    unofficial: bool,
+}
+
+impl Mirrorlist {
+    /// Look the servers up in the status list, `normalized` holding its
+    /// normalized urls in the same order
+    fn new(
+        servers: Servers,
+        json: &JsonResponse,
+        normalized: &[String],
+        thresholds: &Thresholds,
+        suggestions: usize,
+        disabled: bool,
+    ) -> Self {
+        let classify = |server: String| mirror_state(server, json, normalized);
+        let mirrors: Vec<MirrorState> = servers.urls.into_iter().map(classify).collect();
+        let disabled = if disabled {
+            servers.disabled.into_iter().map(classify).collect()
+        } else {
+            vec![]
+        };
+        let unofficial = servers.repository.is_some()
+            && !mirrors.is_empty()
+            && mirrors
+                .iter()
+                .all(|state| matches!(state, MirrorState::NotFound(_)));
+        if unofficial {
+            return Mirrorlist {
+                path: servers.path,
+                repository: servers.repository,
+                mirrors,
+                suggestions: vec![],
+                diagnoses: vec![],
+                disabled,
+                unofficial,
+            };
+        }
+        let diagnoses = mirrors
+            .iter()
+            .filter_map(|state| match state {
+                MirrorState::NotFound(server) => Some(diagnose(server, &json.urls)),
+                _ => None,
+            })
+            .collect();
+        Mirrorlist {
+            path: servers.path,
+            repository: servers.repository,
+            suggestions: suggest(&mirrors, json, thresholds, suggestions),
+            mirrors,
+            diagnoses,
+            disabled,
+            unofficial,
+        }
+    }
+
+    fn name(&self) -> String {
+        match &self.repository {
+            Some(repository) => format!("[{}]", repository),
+            None => mirrorlist_name(&self.path).to_string(),
+        }
+    }
+}
+
+/// The servers of a mirrorlist file, or of a pacman.conf repository
+struct Servers {
+    path: String,
+    repository: Option<String>,
+    urls: Vec<String>,
+    /// The commented-out servers
+    disabled: Vec<String>,
+}
+
+fn mirrorlist_name(path: &str) -> &str {
+    if path == STDIN_PATH {
+        "stdin"
+    } else {
+        path
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum MirrorState {
     NotFound(String),
@@ -123,16 +621,41 @@ pub enum MirrorState {
     OutOfSync(Mirror),
 }
 
+impl MirrorState {
+    fn url(&self) -> &str {
+        match self {
+            MirrorState::Synced(mirror) | MirrorState::OutOfSync(mirror) => &mirror.url,
+            MirrorState::NotFound(server) => server,
+        }
+    }
+
+    /// The mirror of the status list, `None` when not found
+    fn mirror(&self) -> Option<&Mirror> {
+        match self {
+            MirrorState::Synced(mirror) | MirrorState::OutOfSync(mirror) => Some(mirror),
+            MirrorState::NotFound(_) => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Mirror {
     url: String,
     protocol: String,
     country: String,
     completion: Option<f64>,
-    delay: Option<(u32, u32)>,
+    delay: Option<u32>,
     duration_avg: Option<f64>,
     duration_stddev: Option<f64>,
     score: Option<f64>,
+    country_code: String,
+    /// Unix time of the last sync, in seconds
+    last_sync: Option<u64>,
+    active: bool,
+    isos: bool,
+    ipv4: bool,
+    ipv6: bool,
+    details: String,
 }
 
 impl Mirror {
@@ -150,8 +673,8 @@ impl Mirror {
 
     fn delay_to_str(&self) -> String {
         if let Some(value) = self.delay {
-            let (hour, minute) = value;
-            format!("{:}:{:>02}", hour, minute)
+            let (hours, minutes) = (value / 3600, value % 3600 / 60);
+            format!("{:}:{:>02}", hours, minutes)
         } else {
             "".to_string()
         }
@@ -181,20 +704,14 @@ impl Mirror {
         }
     }
 
-    fn get_len(&self, field: &'static str) -> Result<usize, Error> {
-        match field {
-            "url" => Ok(self.url.len()),
-            "protocol" => Ok(self.protocol.len()),
-            "country" => Ok(self.country.len()),
-            "completion" => Ok(self.completion_to_str().len()),
-            "delay" => Ok(self.delay_to_str().len()),
-            "duration_avg" => Ok(self.duration_avg_to_str().len()),
-            "duration_stddev" => Ok(self.duration_stddev_to_str().len()),
-            "score" => Ok(self.score_to_str().len()),
-            _ => Err(Error::new(format!(
-                "Mirror does not have a field \"{}\"",
-                field
-            ))),
+    fn last_sync_to_str(&self) -> String {
+        if let Some(value) = self.last_sync {
+            format!(
+                "{} ago",
+                time::format_age(time::now().saturating_sub(value))
+            )
+        } else {
+            "".to_string()
         }
     }
 }
@@ -202,17 +719,8 @@ impl Mirror {
 impl From<&JsonMirror> for Mirror {
     fn from(json: &JsonMirror) -> Self {
         let completion = json.completion_pct.map(|completion| completion * 100f64);
-        let mut delay: Option<(u32, u32)> = None;
-        if let Some(d) = json.delay {
-            if d < 0 {
-                delay = None;
-            } else {
-                let hours = d as f64 / 3600_f64;
-                let normalized_hours = hours.trunc() as u32;
-                let minutes = (hours.fract() * 60_f64).trunc() as u32;
-                delay = Some((normalized_hours, minutes));
-            }
-        }
+        // a negative delay is meaningless, discard it
+        let delay = json.delay.and_then(|d| u32::try_from(d).ok());
         Mirror {
             url: String::from(&json.url),
             protocol: String::from(&json.protocol),
@@ -222,6 +730,13 @@ impl From<&JsonMirror> for Mirror {
             duration_avg: json.duration_avg,
             duration_stddev: json.duration_stddev,
             score: json.score,
+            country_code: String::from(&json.country_code),
+            last_sync: json.last_sync.as_deref().and_then(time::parse_iso8601),
+            active: json.active,
+            isos: json.isos,
+            ipv4: json.ipv4,
+            ipv6: json.ipv6,
+            details: String::from(&json.details),
         }
     }
 }
@@ -255,250 +770,169 @@ pub struct JsonMirror {
     details: String,
 }
 
-struct MaxLength {
-    state: usize,
-    url: usize,
-    protocol: usize,
-    country: usize,
-    completion: usize,
-    delay: usize,
-    duration_avg: usize,
-    duration_stddev: usize,
-    score: usize,
-}
-
-impl MaxLength {
-    fn new(mirrors: &[MirrorState]) -> Result<Self, Error> {
-        let state = cmp::max(find_max_state_len(mirrors), HEADERS[0].len());
-        let url = cmp::max(find_max_len(mirrors, "url")?, HEADERS[1].len());
-        let protocol = cmp::max(find_max_len(mirrors, "protocol")?, HEADERS[2].len());
-        let country = cmp::max(find_max_len(mirrors, "country")?, HEADERS[3].len());
-        let completion = cmp::max(find_max_len(mirrors, "completion")?, HEADERS[4].len());
-        let delay = cmp::max(find_max_len(mirrors, "delay")?, HEADERS[5].len());
-        let duration_avg = cmp::max(find_max_len(mirrors, "duration_avg")?, HEADERS[6].len()) + 1;
-        let duration_stddev =
-            cmp::max(find_max_len(mirrors, "duration_stddev")?, HEADERS[7].len()) + 1;
-        let score = cmp::max(find_max_len(mirrors, "score")?, HEADERS[8].len());
-        Ok(MaxLength {
-            state,
-            url,
-            protocol,
-            country,
-            completion,
-            delay,
-            duration_avg,
-            duration_stddev,
-            score,
-        })
+impl JsonMirror {
+    /// Follow the mirror status page rule: a mirror is out of sync when
+    /// it is inactive, has never been checked or its last sync is older
+    /// than the cutoff (in seconds)
+    fn is_synced(&self, cutoff: u32) -> bool {
+        match self.delay {
+            Some(delay) if self.active && delay > 0 => delay as u32 <= cutoff,
+            _ => false,
+        }
     }
 }
 
-fn find_max_state_len(mirrors: &[MirrorState]) -> usize {
-    let mut max_len = 0;
-    for mirror_state in mirrors {
-        match mirror_state {
-            MirrorState::NotFound(_) => {
-                if NOT_FOUND.len() > max_len {
-                    max_len = NOT_FOUND.len();
-                }
-            }
-            MirrorState::OutOfSync(_) => {
-                if OUT_OF_SYNC.len() > max_len {
-                    max_len = OUT_OF_SYNC.len();
-                }
-            }
-            MirrorState::Synced(_) => {
-                if OK.len() > max_len {
-                    max_len = OK.len();
-                }
+fn print_diagnoses(diagnoses: &[Diagnosis], painter: &Painter) {
+    for diagnosis in diagnoses {
+        if diagnosis.closest.is_empty() {
+            println!(
+                "{}{}{} is not found, no mirror of the status list is hosted on {}",
+                painter.paint(Bold),
+                diagnosis.url,
+                painter.paint(Reset),
+                diagnosis.host
+            );
+        } else {
+            println!(
+                "{}{}{} is not found, known urls on {}:",
+                painter.paint(Bold),
+                diagnosis.url,
+                painter.paint(Reset),
+                diagnosis.host
+            );
+            for url in &diagnosis.closest {
+                println!("  {}", url);
             }
         }
+        println!();
     }
-    max_len
 }
 
-fn find_max_len(mirrors: &[MirrorState], key: &'static str) -> Result<usize, Error> {
-    let mut max_len = 0;
-    for mirror_state in mirrors {
-        match mirror_state {
-            MirrorState::NotFound(server) => {
-                if key == "url" && server.len() > max_len {
-                    max_len = server.len();
-                }
-            }
-            MirrorState::OutOfSync(mirror) => {
-                let value = mirror.get_len(key)?;
-                if value > max_len {
-                    max_len = value;
-                }
-            }
-            MirrorState::Synced(mirror) => {
-                let value = mirror.get_len(key)?;
-                if value > max_len {
-                    max_len = value;
-                }
-            }
+fn print_suggestions(suggestions: &[Suggestion], painter: &Painter) {
+    for suggestion in suggestions {
+        println!(
+            "{}replacements for {}{} ({}, {}):",
+            painter.paint(Bold),
+            suggestion.url,
+            painter.paint(Reset),
+            suggestion.country_code,
+            suggestion.protocol
+        );
+        for mirror in &suggestion.mirrors {
+            println!("Server = {}$repo/os/$arch", mirror.url);
         }
+        println!();
     }
-    Ok(max_len)
-}
-
-fn print_headers(max_len: &MaxLength) {
-    println!(
-        "{}{} {} {} {} {} {} {} {} {}{}",
-        Bold,
-        format!("{:>width$}", HEADERS[0], width = max_len.state),
-        format!("{:<width$}", HEADERS[1], width = max_len.url),
-        format!("{:<width$}", HEADERS[2], width = max_len.protocol),
-        format!("{:<width$}", HEADERS[3], width = max_len.country),
-        format!("{:>width$}", HEADERS[4], width = max_len.completion),
-        format!("{:>width$}", HEADERS[5], width = max_len.delay),
-        format!("{:>width$}", HEADERS[6], width = max_len.duration_avg),
-        format!("{:>width$}", HEADERS[7], width = max_len.duration_stddev),
-        format!("{:>width$}", HEADERS[8], width = max_len.score),
-        Reset
-    );
 }
 
-fn print_mirror<C: Color + Copy>(
-    max_len: &MaxLength,
-    mirror: &Mirror,
-    state: &'static str,
-    color: C,
-) {
-    let completion_color = if let Some(value) = mirror.completion {
-        if (95f64..100f64).contains(&value) {
-            format!("{}", Fg(Yellow))
-        } else if value < 95f64 {
-            format!("{}", Fg(Red))
-        } else {
-            "".to_string()
-        }
-    } else {
-        "".to_string()
+fn print_findings(name: &str, findings: &[Finding], painter: &Painter) {
+    for finding in findings {
+        let location = match finding.line {
+            Some(line) => format!("{}:{}", name, line),
+            None => name.to_string(),
+        };
+        let color = match finding.severity {
+            Severity::Warning => painter.paint(Fg(Yellow)),
+            Severity::Error => painter.paint(Fg(Red)),
+        };
+        println!(
+            "{}: {}{}{}:{} {}",
+            location,
+            painter.paint(Bold),
+            color,
+            finding.severity,
+            painter.paint(Reset),
+            finding.message
+        );
+    }
+    let count = |severity| {
+        findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
     };
-    let delay_color = if let Some(value) = mirror.delay {
-        let (hours, minutes) = value;
-        if hours > 0 {
-            format!("{}", Fg(Red))
-        } else if minutes > 30 {
-            format!("{}", Fg(Yellow))
-        } else {
-            "".to_string()
-        }
+    if findings.is_empty() {
+        println!("{}: no problem found", name);
     } else {
-        "".to_string()
-    };
-    let score_color = if let Some(value) = mirror.score {
-        if value > 2f64 {
-            format!("{}", Fg(Red))
-        } else if value > 1f64 {
-            format!("{}", Fg(Yellow))
-        } else {
-            "".to_string()
-        }
+        println!(
+            "{}: {} error(s), {} warning(s)",
+            name,
+            count(Severity::Error),
+            count(Severity::Warning)
+        );
+    }
+}
+
+fn read_mirrorlist(path: &str) -> Result<String, String> {
+    if path == STDIN_PATH {
+        let mut mirrorlist = String::new();
+        io::stdin()
+            .read_to_string(&mut mirrorlist)
+            .map_err(|err| format!("an error occured while reading stdin: {}", err))?;
+        Ok(mirrorlist)
     } else {
-        "".to_string()
-    };
-    println!(
-        "{} {} {} {} {} {} {} {} {}",
-        format!(
-            "{}{}{:>width$}{}",
-            Bold,
-            Fg(color),
-            state,
-            Reset,
-            width = max_len.state
-        ),
-        format!("{:<width$}", mirror.url, width = max_len.url),
-        format!("{:<width$}", mirror.protocol, width = max_len.protocol),
-        format!("{:<width$}", mirror.country, width = max_len.country),
-        format!(
-            "{}{:>width$}{}",
-            completion_color,
-            mirror.completion_to_str(),
-            Fg(ColorReset),
-            width = max_len.completion,
-        ),
-        format!(
-            "{}{:>width$}{}",
-            delay_color,
-            mirror.delay_to_str(),
-            Fg(ColorReset),
-            width = max_len.delay
-        ),
-        format!(
-            "{:>width$}",
-            mirror.duration_avg_to_str(),
-            width = max_len.duration_avg
-        ),
-        format!(
-            "{:>width$}",
-            mirror.duration_stddev_to_str(),
-            width = max_len.duration_stddev
-        ),
-        format!(
-            "{}{:>width$}{}",
-            score_color,
-            mirror.score_to_str(),
-            Fg(ColorReset),
-            width = max_len.score
-        ),
-    );
+        fs::read_to_string(path)
+            .map_err(|err| format!("an error occured while reading the file {}: {}", path, err))
+    }
 }
 
-fn print_mirrors(mirrors: Vec<MirrorState>) -> Result<(), Error> {
-    let max_lengths = MaxLength::new(&mirrors)?;
-    print_headers(&max_lengths);
-    for mirror_state in &mirrors {
-        match mirror_state {
-            MirrorState::NotFound(server) => {
-                println!(
-                    "{}{}{}{} {}",
-                    Bold,
-                    Fg(Yellow),
-                    format!("{:>width$}", NOT_FOUND, width = max_lengths.state),
-                    Reset,
-                    server
-                );
-            }
-            MirrorState::OutOfSync(mirror) => {
-                print_mirror(&max_lengths, mirror, OUT_OF_SYNC, Red);
-            }
-            MirrorState::Synced(mirror) => {
-                print_mirror(&max_lengths, mirror, OK, Green);
-            }
-        }
+/// Read the `Server` entries of a mirrorlist, the `CacheServer` ones are
+/// local caches unknown to the mirror status. With `disabled`, a file of
+/// commented-out servers only is accepted.
+fn parse_mirrorlist(path: &str, disabled: bool) -> Result<mirrorlist::Mirrorlist, String> {
+    let mirrorlist = mirrorlist::parse(&read_mirrorlist(path)?);
+    print_problems(mirrorlist_name(path), &mirrorlist.problems);
+    let has_servers = !mirrorlist.servers().is_empty();
+    if !has_servers && (!disabled || mirrorlist.disabled.is_empty()) {
+        Err(format!("no server found in {}", mirrorlist_name(path)))
+    } else {
+        Ok(mirrorlist)
     }
-    println!();
-    Ok(())
 }
 
-fn parse_mirrorlist() -> Result<Vec<String>, String> {
-    let mut mirrors = vec![];
-    let mirrorlist = fs::read_to_string(PACMAN_MIRRORLIST).map_err(|err| {
-        format!(
-            "an error occured while reading the file {}: {}",
-            PACMAN_MIRRORLIST, err
-        )
-    })?;
-    for line in mirrorlist.lines() {
-        if let Some(url) = line.strip_prefix("Server = ") {
-            if line.ends_with("/$repo/os/$arch") {
-                let end = url.len() - 14;
-                mirrors.push(String::from(&url[..end]));
-            } else if line.ends_with("/$repo/os/$arch/") {
-                let end = url.len() - 15;
-                mirrors.push(String::from(&url[..end]));
-            } else {
-                mirrors.push(String::from(url));
-            }
+/// Get the content at `url`, from the cache if fresh enough. When
+/// offline, the cached content is used whatever its age. Otherwise the
+/// request is conditional and the cached content is used when it did
+/// not change on the server side.
+fn fetch(url: &str, client: &Client, milcheck: &Milcheck) -> Result<String, Error> {
+    let cache = &milcheck.cache;
+    let entry = cache.get(url);
+    match entry {
+        Some(entry) if milcheck.offline || cache.is_fresh(&entry) => return Ok(entry.body),
+        None if milcheck.offline => {
+            return Err(Error::new(format!(
+                "no cached data for {}, run milcheck online first",
+                url
+            )))
         }
+        _ => {}
     }
-    if mirrors.is_empty() {
-        Err(format!("no server found in {}", PACMAN_MIRRORLIST))
-    } else {
-        Ok(mirrors)
+    let validators = entry
+        .as_ref()
+        .map(|entry| entry.validators.clone())
+        .unwrap_or_default();
+    let (body, validators) = match Http::get(client, &milcheck.network, url, &validators).wait()? {
+        Response::Body(body, validators) => (body, validators),
+        Response::NotModified => match entry {
+            Some(entry) => (entry.body, entry.validators),
+            None => return Err(Error::new(format!("unexpected 304 response from {}", url))),
+        },
+    };
+    // the cache is best effort, failing to write it must not prevent
+    // from printing the result
+    let _ = cache.store(url, &body, &validators);
+    Ok(body)
+}
+
+/// Look a server up in the status list, `normalized` holding the
+/// normalized urls of the status list in the same order
+fn mirror_state(server: String, json: &JsonResponse, normalized: &[String]) -> MirrorState {
+    let server_normalized = normalize(&server);
+    let position = normalized.iter().position(|url| *url == server_normalized);
+    match position.map(|i| &json.urls[i]) {
+        Some(mirror) if mirror.is_synced(json.cutoff) => MirrorState::Synced(Mirror::from(mirror)),
+        Some(mirror) => MirrorState::OutOfSync(Mirror::from(mirror)),
+        None => MirrorState::NotFound(server),
     }
 }
 
@@ -506,67 +940,336 @@ pub fn logic(
     tx: Sender<&'static str>,
     rx: Receiver<&'static str>,
     render: &mut Render,
-    print_mirrorlist: bool,
-    print_news: bool,
-    last: Option<u8>,
-) -> Result<(Option<Vec<MirrorState>>, Option<String>), Error> {
+    milcheck: &Milcheck,
+    client: &Client,
+) -> Result<(Option<Vec<Mirrorlist>>, Option<String>), Error> {
     let mut mirrors = None;
     let mut news_text = None;
-    render.run(rx);
-    if print_mirrorlist {
-        let mut parsed = vec![];
+    let mut mirrorlists = vec![];
+    if milcheck.print_mirrorlist {
         tx.send("parsing local mirrorlist")?;
-        let mirrorlist = parse_mirrorlist()?;
+        // read the files before the render starts listening stdin for keys
+        mirrorlists = match &milcheck.source {
+            Source::Files(files) => files
+                .iter()
+                .map(|path| {
+                    parse_mirrorlist(path, milcheck.disabled).map(|mirrorlist| Servers {
+                        path: path.to_string(),
+                        repository: None,
+                        urls: mirrorlist.servers(),
+                        disabled: mirrorlist.disabled_servers(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Source::PacmanConf(path) => parse_pacman_conf(path)?
+                .into_iter()
+                .map(|repository| Servers {
+                    path: path.to_string(),
+                    repository: Some(repository.name),
+                    urls: repository.servers,
+                    disabled: repository.disabled,
+                })
+                .collect(),
+        };
+    }
+    render.run(rx);
+    if milcheck.print_mirrorlist {
+        let mut lists = vec![];
         tx.send("fetching mirror status list")?;
-        let request = Http::get(MIRROR_STATUS_URL);
-        let json_request = Http::get(MIRROR_STATUS_JSON_URL);
-        let response = request.wait()?;
-        let json_response = json_request.wait()?;
+        let json_response = fetch(&milcheck.status_url, client, milcheck)?;
         tx.send("deserialize json data")?;
         let json: JsonResponse = serde_json::from_str(&json_response)
             .map_err(|err| format!("json response parsing failed: {}", err))?;
-        tx.send("web scraping")?;
-        let v: Vec<&str> = response.split("</table>").collect();
-        if v.len() != 4 {
-            return Err(Error::new("web scraping failed"));
-        }
-        if !v[0].contains(OUTOFSYNC_HTML_TAG) {
-            return Err(Error::new("web scraping failed"));
-        }
-        if !v[1].contains(INSYNC_HTML_TAG) {
-            return Err(Error::new("web scraping failed"));
-        }
         tx.send("building data")?;
-        for server in mirrorlist {
-            if let Some(mirror) = json.urls.iter().find(|&mirror| mirror.url == server) {
-                if let Some(_i) = v[0].find(&server) {
-                    parsed.push(MirrorState::OutOfSync(Mirror::from(mirror)));
-                } else {
-                    parsed.push(MirrorState::Synced(Mirror::from(mirror)));
-                }
-            } else {
-                parsed.push(MirrorState::NotFound(server));
-            }
+        let normalized: Vec<String> = json.urls.iter().map(|m| normalize(&m.url)).collect();
+        for servers in mirrorlists {
+            lists.push(Mirrorlist::new(
+                servers,
+                &json,
+                &normalized,
+                &milcheck.thresholds,
+                milcheck.suggestions,
+                milcheck.disabled,
+            ));
         }
-        mirrors = Some(parsed);
+        mirrors = Some(lists);
     }
-    if print_news {
+    if milcheck.print_news {
         tx.send("fetching latest news")?;
-        let mut arch_org_request = None;
-        if print_news {
-            arch_org_request = Some(Http::get(ARCHLINUX_ORG_URL));
-        }
-        let mut org_response = None;
-        if let Some(req) = arch_org_request {
-            org_response = Some(req.wait()?);
-        }
-        if org_response.is_none() {
-            return Err(Error::new("fail to fetch archlinux.org data"));
-        }
+        let org_response = fetch(&milcheck.archlinux_url, client, milcheck)?;
         tx.send("parsing news data")?;
-        let mut news_parser = News::new(org_response.unwrap(), ARCHLINUX_ORG_URL, last);
+        let mut news_parser = News::new(
+            org_response,
+            milcheck.archlinux_url.trim_end_matches('/'),
+            milcheck.last,
+            milcheck.painter,
+        );
         news_text = Some(news_parser.parse()?);
     };
     tx.send("done")?;
     Ok((mirrors, news_text))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// A mirror of the status, the tests of the other modules override
+    /// its fields
+    pub(crate) fn mirror(score: f64) -> Mirror {
+        Mirror {
+            url: "https://a.example.org/".to_string(),
+            protocol: "https".to_string(),
+            country: "France".to_string(),
+            completion: Some(100.0),
+            delay: Some(600),
+            duration_avg: Some(0.3),
+            duration_stddev: Some(0.1),
+            score: Some(score),
+            country_code: "FR".to_string(),
+            last_sync: None,
+            active: true,
+            isos: true,
+            ipv4: true,
+            ipv6: true,
+            details: String::new(),
+        }
+    }
+
+    /// A mirror of the status API, synced when its delay is below the
+    /// cutoff of `json_response`
+    pub(crate) fn json_mirror(url: &str, country_code: &str, delay: Option<i32>) -> JsonMirror {
+        JsonMirror {
+            url: url.to_string(),
+            protocol: url
+                .split_once("://")
+                .map_or("", |(scheme, _)| scheme)
+                .to_string(),
+            country: country_code.to_string(),
+            country_code: country_code.to_string(),
+            completion_pct: Some(1.0),
+            delay,
+            duration_avg: Some(0.3),
+            duration_stddev: Some(0.1),
+            score: Some(0.5),
+            last_sync: None,
+            active: true,
+            isos: true,
+            ipv4: true,
+            ipv6: true,
+            details: String::new(),
+        }
+    }
+
+    pub(crate) fn json_response(urls: Vec<JsonMirror>) -> JsonResponse {
+        JsonResponse {
+            cutoff: 3600,
+            last_check: String::new(),
+            num_checks: 1,
+            check_frequency: 1,
+            urls,
+            version: 3,
+        }
+    }
+
+    fn synced() -> MirrorState {
+        MirrorState::Synced(mirror(0.5))
+    }
+
+    fn critical() -> MirrorState {
+        MirrorState::Synced(mirror(5.0))
+    }
+
+    fn out_of_sync() -> MirrorState {
+        MirrorState::OutOfSync(mirror(0.5))
+    }
+
+    fn not_found() -> MirrorState {
+        MirrorState::NotFound("https://b.example.org/".to_string())
+    }
+
+    fn code(mirrors: &[MirrorState]) -> i32 {
+        Status::new(mirrors, &Thresholds::default()).code()
+    }
+
+    #[test]
+    fn status_codes() {
+        assert_eq!(code(&[synced(), synced()]), 0);
+        assert_eq!(code(&[synced(), out_of_sync()]), 2);
+        assert_eq!(code(&[synced(), not_found()]), 3);
+        assert_eq!(code(&[out_of_sync(), not_found()]), 4);
+        assert_eq!(code(&[]), 4);
+        assert_eq!(code(&[synced(), critical()]), 5);
+    }
+
+    #[test]
+    fn status_precedence() {
+        // 4 then 2 then 3 then 5
+        assert_eq!(code(&[out_of_sync(), not_found(), critical()]), 2);
+        assert_eq!(code(&[critical(), out_of_sync(), not_found()]), 2);
+        assert_eq!(code(&[critical(), not_found()]), 3);
+        assert_eq!(code(&[out_of_sync(), out_of_sync()]), 4);
+        assert!(Status::Unusable > Status::OutOfSync);
+        assert!(Status::OutOfSync > Status::NotFound);
+        assert!(Status::NotFound > Status::Critical);
+        assert!(Status::Critical > Status::Synced);
+    }
+
+    fn evaluate(repository: Option<&str>, urls: &[&str], json: &JsonResponse) -> Mirrorlist {
+        let normalized: Vec<String> = json.urls.iter().map(|m| normalize(&m.url)).collect();
+        let servers = Servers {
+            path: PACMAN_CONF.to_string(),
+            repository: repository.map(String::from),
+            urls: urls.iter().map(|url| url.to_string()).collect(),
+            disabled: vec![],
+        };
+        Mirrorlist::new(servers, json, &normalized, &Thresholds::default(), 3, false)
+    }
+
+    #[test]
+    fn unofficial_repositories_are_not_checked() {
+        let json = json_response(vec![
+            json_mirror("https://a.example.org/", "FR", Some(600)),
+            json_mirror("https://b.example.org/", "FR", Some(600)),
+        ]);
+        let core = evaluate(Some("core"), &["https://a.example.org/"], &json);
+        let chaotic = evaluate(
+            Some("chaotic-aur"),
+            &["https://cdn-mirror.chaotic.cx/", "file:///srv/repo/"],
+            &json,
+        );
+        assert!(!core.unofficial);
+        assert!(chaotic.unofficial);
+        assert!(chaotic.diagnoses.is_empty());
+        assert!(chaotic.suggestions.is_empty());
+        let lists = [core, chaotic];
+        assert_eq!(
+            Status::of(&lists, &Thresholds::default()),
+            Some(Status::Synced)
+        );
+        assert_eq!(Status::of(&lists[1..], &Thresholds::default()), None);
+        let report = Report::new(&lists, &lists, &Thresholds::default())
+            .to_json()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        assert_eq!(report["mirrorlists"][1]["unofficial"], true);
+        assert!(report["mirrorlists"][1]["warnings"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn mirrorlist_files_are_never_unofficial() {
+        let json = json_response(vec![json_mirror("https://a.example.org/", "FR", Some(600))]);
+        let list = evaluate(None, &["https://c.example.org/"], &json);
+        assert!(!list.unofficial);
+        assert_eq!(list.diagnoses.len(), 1);
+        assert_eq!(
+            Status::of(&[list], &Thresholds::default()),
+            Some(Status::Unusable)
+        );
+        // a repository with a single known server is official
+        let list = evaluate(
+            Some("core"),
+            &["https://c.example.org/", "https://a.example.org/"],
+            &json,
+        );
+        assert!(!list.unofficial);
+    }
+
+    /// A milcheck reading no configuration file, with a cache in a fresh
+    /// directory
+    fn milcheck(name: &str, args: &[&str]) -> Milcheck {
+        let dir = env::temp_dir().join(format!("milcheck-fetch-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+        fs::write(&config, "").unwrap();
+        let config = config.to_string_lossy().into_owned();
+        let cli = Cli::try_parse_from(
+            ["milcheck", "--config", &config, "--retries", "0"]
+                .iter()
+                .chain(args),
+        )
+        .unwrap();
+        let mut milcheck = Milcheck::try_from(cli).unwrap();
+        milcheck.cache = Cache::with_dir(Some(dir.join("cache")), 0);
+        milcheck
+    }
+
+    /// Answer a single request with a 304, returns the url to request
+    fn not_modified_server() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/json/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            use std::io::{BufRead, BufReader, Write};
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+        url
+    }
+
+    fn client(milcheck: &Milcheck) -> Client {
+        http::client(&milcheck.network).unwrap()
+    }
+
+    #[test]
+    fn offline_without_cached_data() {
+        let milcheck = milcheck("offline-none", &["--offline"]);
+        let err = fetch("https://a/json/", &client(&milcheck), &milcheck).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no cached data for https://a/json/, run milcheck online first"
+        );
+    }
+
+    #[test]
+    fn offline_serves_stale_data() {
+        let milcheck = milcheck("offline-stale", &["--offline"]);
+        let validators = http::Validators::default();
+        milcheck
+            .cache
+            .store("https://a/json/", "stale", &validators)
+            .unwrap();
+        let body = fetch("https://a/json/", &client(&milcheck), &milcheck).unwrap();
+        assert_eq!(body, "stale");
+        // not the entry of another url sharing the file name
+        assert!(fetch("http://a/json/", &client(&milcheck), &milcheck).is_err());
+    }
+
+    #[test]
+    fn not_modified_without_cached_data() {
+        let milcheck = milcheck("304-none", &[]);
+        let url = not_modified_server();
+        let err = fetch(&url, &client(&milcheck), &milcheck).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("unexpected 304 response from {}", url)
+        );
+    }
+
+    #[test]
+    fn not_modified_serves_cached_data() {
+        let milcheck = milcheck("304-stale", &[]);
+        let url = not_modified_server();
+        let validators = http::Validators {
+            etag: Some("\"1\"".to_string()),
+            last_modified: None,
+        };
+        milcheck.cache.store(&url, "cached", &validators).unwrap();
+        assert_eq!(
+            fetch(&url, &client(&milcheck), &milcheck).unwrap(),
+            "cached"
+        );
+    }
+}
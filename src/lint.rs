@@ -0,0 +1,303 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::matching::{diagnose, host, normalize};
+use crate::mirrorlist::{Directive, Entry, Mirrorlist, Severity};
+use crate::order::Order;
+use crate::thresholds::Thresholds;
+use crate::{mirror_state, JsonResponse, MirrorState};
+use serde::Serialize;
+
+/// Protocols pacman downloads over, through libcurl
+const PROTOCOLS: [&str; 4] = ["http", "https", "ftp", "file"];
+/// Protocols sending the packages in clear
+const PLAIN_PROTOCOLS: [&str; 2] = ["http", "ftp"];
+
+/// A problem of a mirrorlist
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// Line number, from 1, `None` when about the whole file
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Check the syntax of a mirrorlist, the urls of its servers, their
+/// presence in the status list and their order. The findings are sorted
+/// by line, the ones about the whole file first.
+pub fn lint(mirrorlist: &Mirrorlist, json: &JsonResponse, thresholds: &Thresholds) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = mirrorlist
+        .problems
+        .iter()
+        .map(|problem| Finding {
+            line: Some(problem.line),
+            severity: problem.severity,
+            message: problem.message.clone(),
+        })
+        .collect();
+    let valid: Vec<bool> = mirrorlist
+        .entries
+        .iter()
+        .map(|entry| check_url(entry, &mut findings))
+        .collect();
+    let servers: Vec<(&Entry, bool)> = mirrorlist
+        .entries
+        .iter()
+        .zip(valid)
+        .filter(|(entry, _)| entry.directive == Directive::Server)
+        .collect();
+    if servers.is_empty() {
+        findings.push(Finding {
+            line: None,
+            severity: Severity::Error,
+            message: "no server found".to_string(),
+        });
+    }
+    let normalized: Vec<String> = json.urls.iter().map(|m| normalize(&m.url)).collect();
+    let mirrors: Vec<MirrorState> = servers
+        .iter()
+        .map(|(entry, _)| mirror_state(entry.base(), json, &normalized))
+        .collect();
+    for ((entry, valid), state) in servers.iter().zip(&mirrors) {
+        // an invalid url is already reported
+        if let (true, MirrorState::NotFound(server)) = (valid, state) {
+            let diagnosis = diagnose(server, &json.urls);
+            let message = match diagnosis.closest.first() {
+                Some(closest) => format!(
+                    "{} is not found in the mirror status list, did you mean {}?",
+                    server, closest
+                ),
+                None => format!("{} is not found in the mirror status list", server),
+            };
+            findings.push(Finding {
+                line: Some(entry.line),
+                severity: Severity::Warning,
+                message,
+            });
+        }
+    }
+    for (index, warning) in Order::new(&mirrors, thresholds).positioned_warnings() {
+        findings.push(Finding {
+            line: index.map(|index| servers[index].0.line),
+            // without a healthy mirror, pacman cannot download anything
+            severity: match index {
+                Some(_) => Severity::Warning,
+                None => Severity::Error,
+            },
+            message: warning,
+        });
+    }
+    findings.sort_by_key(|finding| finding.line);
+    findings
+}
+
+/// Check an url the way pacman and libcurl would take it, false when
+/// no download could succeed with it
+fn check_url(entry: &Entry, findings: &mut Vec<Finding>) -> bool {
+    let mut finding = |severity: Severity, message: String| {
+        findings.push(Finding {
+            line: Some(entry.line),
+            severity,
+            message,
+        })
+    };
+    let url = &entry.url;
+    let scheme = match url.split_once("://") {
+        Some((scheme, _)) => scheme.to_ascii_lowercase(),
+        None => {
+            finding(Severity::Error, format!("{} is not an url", url));
+            return false;
+        }
+    };
+    if !PROTOCOLS.contains(&scheme.as_str()) {
+        finding(
+            Severity::Error,
+            format!(
+                "unsupported protocol {}, pacman downloads over {}",
+                scheme,
+                PROTOCOLS.join(", ")
+            ),
+        );
+        return false;
+    }
+    let mut valid = true;
+    if scheme != "file" && host(url).is_empty() {
+        finding(Severity::Error, format!("{} has no host", url));
+        valid = false;
+    }
+    if url.contains(char::is_whitespace) {
+        finding(Severity::Error, format!("{} contains a whitespace", url));
+        valid = false;
+    }
+    // a cache server is usually a plain http server of the local network
+    if entry.directive == Directive::Server && PLAIN_PROTOCOLS.contains(&scheme.as_str()) {
+        finding(
+            Severity::Warning,
+            format!("{} is a plain {} mirror, prefer https", url, scheme),
+        );
+    }
+    if !url.contains("$repo") {
+        finding(
+            Severity::Error,
+            format!(
+                "{} has no $repo, every repository would get the same databases",
+                url
+            ),
+        );
+    }
+    if !url.contains("$arch") {
+        finding(Severity::Warning, format!("{} has no $arch", url));
+    }
+    valid
+}
+
+/// Exit code of the lint: 0 nothing found, 2 some warnings, 3 some errors
+pub fn code(findings: &[Finding]) -> i32 {
+    match findings.iter().map(|finding| finding.severity).max() {
+        None => 0,
+        Some(Severity::Warning) => 2,
+        Some(Severity::Error) => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mirrorlist;
+    use crate::tests::{json_mirror, json_response};
+
+    /// The findings of a mirrorlist, the synced mirrors of the status
+    /// list being `a` and `b` over https, `c` over http
+    fn findings(content: &str) -> Vec<(Option<usize>, Severity, String)> {
+        let json = json_response(vec![
+            json_mirror("https://a.example.org/", "FR", Some(600)),
+            json_mirror("https://b.example.org/", "FR", Some(600)),
+            json_mirror("http://c.example.org/", "FR", Some(600)),
+        ]);
+        lint(&mirrorlist::parse(content), &json, &Thresholds::default())
+            .into_iter()
+            .map(|finding| (finding.line, finding.severity, finding.message))
+            .collect()
+    }
+
+    /// A healthy first server, so that the order of the others is fine
+    fn after_a(line: &str) -> Vec<(Option<usize>, Severity, String)> {
+        findings(&format!(
+            "Server = https://a.example.org/$repo/os/$arch\n{}\n",
+            line
+        ))
+    }
+
+    fn finding(
+        line: usize,
+        severity: Severity,
+        message: &str,
+    ) -> (Option<usize>, Severity, String) {
+        (Some(line), severity, message.to_string())
+    }
+
+    #[test]
+    fn clean_mirrorlist() {
+        assert!(after_a("Server = https://b.example.org/$repo/os/$arch").is_empty());
+    }
+
+    #[test]
+    fn unsupported_scheme() {
+        assert_eq!(
+            after_a("Server = rsync://b.example.org/$repo/os/$arch"),
+            [finding(
+                2,
+                Severity::Error,
+                "unsupported protocol rsync, pacman downloads over http, https, ftp, file"
+            )]
+        );
+    }
+
+    #[test]
+    fn missing_host() {
+        assert_eq!(
+            after_a("Server = https:///$repo/os/$arch"),
+            [finding(
+                2,
+                Severity::Error,
+                "https:///$repo/os/$arch has no host"
+            )]
+        );
+        // a local directory has none
+        assert!(after_a("Server = file:///srv/arch/$repo/os/$arch")
+            .iter()
+            .all(|(_, _, message)| !message.contains("no host")));
+    }
+
+    #[test]
+    fn missing_variables() {
+        assert_eq!(
+            after_a("Server = https://b.example.org/"),
+            [
+                finding(
+                    2,
+                    Severity::Error,
+                    "https://b.example.org/ has no $repo, every repository would get the same databases"
+                ),
+                finding(2, Severity::Warning, "https://b.example.org/ has no $arch"),
+            ]
+        );
+        assert_eq!(
+            after_a("Server = https://b.example.org/$repo/os/"),
+            [finding(
+                2,
+                Severity::Warning,
+                "https://b.example.org/$repo/os/ has no $arch"
+            )]
+        );
+    }
+
+    #[test]
+    fn plain_http_servers_only() {
+        assert_eq!(
+            after_a("Server = http://c.example.org/$repo/os/$arch"),
+            [finding(
+                2,
+                Severity::Warning,
+                "http://c.example.org/$repo/os/$arch is a plain http mirror, prefer https"
+            )]
+        );
+        assert!(after_a("CacheServer = http://cache.lan/$repo/os/$arch").is_empty());
+    }
+
+    #[test]
+    fn no_server_found() {
+        assert_eq!(
+            findings("## header\nCacheServer = http://cache.lan/$repo/os/$arch\n"),
+            [(None, Severity::Error, "no server found".to_string())]
+        );
+    }
+
+    #[test]
+    fn findings_sorted_by_line() {
+        let lines: Vec<Option<usize>> = findings(
+            "Server = https://d.example.org/$repo/os/$arch\nServer = https://a.example.org/$repo/os/\n",
+        )
+        .into_iter()
+        .map(|(line, _, _)| line)
+        .collect();
+        assert_eq!(lines, [Some(1), Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn codes() {
+        let finding = |severity: Severity| Finding {
+            line: None,
+            severity,
+            message: String::new(),
+        };
+        assert_eq!(code(&[]), 0);
+        assert_eq!(code(&[finding(Severity::Warning)]), 2);
+        assert_eq!(
+            code(&[finding(Severity::Warning), finding(Severity::Error)]),
+            3
+        );
+        assert_eq!(code(&[finding(Severity::Error)]), 3);
+    }
+}
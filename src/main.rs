@@ -8,10 +8,25 @@ use milcheck::Milcheck;
 use std::process;
 
 fn main() {
-    let cli = Cli::parse();
-    let mut milcheck = Milcheck::from(cli);
-    milcheck.run().unwrap_or_else(|err| {
-        eprintln!("error: {}", err);
-        process::exit(1);
+    // Rust ignores SIGPIPE, printing to a closed pipe, e.g. `| head`,
+    // would panic instead of stopping quietly as the other tools do
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+    let cli = Cli::try_parse().unwrap_or_else(|err| {
+        // clap exits with 2 on a usage error, the code of the out of sync
+        // mirrors, take it as a runtime error instead
+        let code = if err.use_stderr() { 1 } else { 0 };
+        let _ = err.print();
+        process::exit(code);
     });
+    let code = Milcheck::try_from(cli)
+        .and_then(|mut milcheck| milcheck.run())
+        .unwrap_or_else(|err| {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        });
+    if let Some(code) = code {
+        process::exit(code);
+    }
 }
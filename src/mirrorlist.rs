@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use serde::Serialize;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+pub const SERVER: &str = "Server";
+const CACHE_SERVER: &str = "CacheServer";
+
+/// A meaningful line of a pacman configuration file, read the way pacman
+/// reads it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line<'a> {
+    Section(&'a str),
+    /// A `key = value` line, or a lone key for the boolean options
+    Directive(&'a str, Option<&'a str>),
+}
+
+/// Parse a line as pacman does: a `#` starts a comment wherever it is,
+/// the whitespaces around the line, the key and the value are ignored.
+/// `None` for a blank or a comment line.
+pub fn parse_line(line: &str) -> Option<Line<'_>> {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    if line.is_empty() {
+        return None;
+    }
+    if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+        return Some(Line::Section(section.trim()));
+    }
+    Some(match line.split_once('=') {
+        Some((key, value)) => Line::Directive(key.trim(), Some(value.trim())),
+        None => Line::Directive(line, None),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Directive {
+    Server,
+    /// A server tried before the others, usually a local cache, see
+    /// pacman.conf(5)
+    CacheServer,
+}
+
+/// A server of a mirrorlist
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// Line number, from 1
+    pub line: usize,
+    pub directive: Directive,
+    /// The url as written, with its variables
+    pub url: String,
+}
+
+impl Entry {
+    pub fn base(&self) -> String {
+        server_base(&self.url)
+    }
+}
+
+/// How pacman takes a problem: it goes on after a warning, an error
+/// breaks the repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A line pacman would complain about or ignore
+#[derive(Debug, Clone)]
+pub struct Problem {
+    /// Line number, from 1
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Print on stderr the lines pacman would complain about or ignore
+pub fn print_problems(name: &str, problems: &[Problem]) {
+    for problem in problems {
+        eprintln!(
+            "{}: {}:{}: {}",
+            problem.severity, name, problem.line, problem.message
+        );
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Mirrorlist {
+    pub entries: Vec<Entry>,
+    /// The commented-out `Server` entries
+    pub disabled: Vec<Entry>,
+    pub problems: Vec<Problem>,
+}
+
+impl Mirrorlist {
+    /// The base urls of the `Server` entries, in order
+    pub fn servers(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.directive == Directive::Server)
+            .map(Entry::base)
+            .collect()
+    }
+
+    /// The base urls of the commented-out `Server` entries, in order
+    pub fn disabled_servers(&self) -> Vec<String> {
+        self.disabled.iter().map(Entry::base).collect()
+    }
+}
+
+/// The url of a commented-out `Server` line, e.g. `#Server = https://...`
+/// as shipped by reflector and the pacman-mirrorlist package. The `##`
+/// lines are prose, e.g. the headers of these files.
+pub fn parse_disabled_line(line: &str) -> Option<&str> {
+    let line = line.trim_start().strip_prefix('#')?;
+    if line.starts_with('#') {
+        return None;
+    }
+    match parse_line(line)? {
+        // the url check skips the prose of the comments
+        Line::Directive(SERVER, Some(url)) if url.contains("://") => Some(url),
+        _ => None,
+    }
+}
+
+/// Parse the content of a mirrorlist, CRLF line endings included
+pub fn parse(content: &str) -> Mirrorlist {
+    let mut mirrorlist = Mirrorlist::default();
+    // `lines` strips the `\r` of the CRLF endings
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let mut problem = |severity: Severity, message: String| {
+            mirrorlist.problems.push(Problem {
+                line: number,
+                severity,
+                message,
+            })
+        };
+        match parse_line(line) {
+            None => {
+                if let Some(url) = parse_disabled_line(line) {
+                    mirrorlist.disabled.push(Entry {
+                        line: number,
+                        directive: Directive::Server,
+                        url: url.to_string(),
+                    });
+                }
+            }
+            // the servers after it would go to another repository
+            Some(Line::Section(section)) => problem(
+                Severity::Error,
+                format!(
+                    "section [{}] in a mirrorlist, only servers are expected",
+                    section
+                ),
+            ),
+            Some(Line::Directive(key, value)) => {
+                let directive = match key {
+                    SERVER => Directive::Server,
+                    CACHE_SERVER => Directive::CacheServer,
+                    _ => {
+                        problem(Severity::Warning, format!("unknown directive '{}'", key));
+                        continue;
+                    }
+                };
+                let url = match value {
+                    Some(url) if !url.is_empty() => url,
+                    _ => {
+                        problem(
+                            Severity::Error,
+                            format!("directive '{}' needs a value", key),
+                        );
+                        continue;
+                    }
+                };
+                let duplicate = mirrorlist
+                    .entries
+                    .iter()
+                    .find(|entry| entry.directive == directive && entry.base() == server_base(url));
+                if let Some(entry) = duplicate {
+                    let line = entry.line;
+                    problem(
+                        Severity::Warning,
+                        format!("duplicate of the server of the line {}", line),
+                    );
+                }
+                mirrorlist.entries.push(Entry {
+                    line: number,
+                    directive,
+                    url: url.to_string(),
+                });
+            }
+        }
+    }
+    mirrorlist
+}
+
+/// Strip the variable part of a server url, e.g. `$repo/os/$arch` or
+/// `$arch/$repo`, leaving the mirror url as listed in the mirror status
+pub fn server_base(url: &str) -> String {
+    let mut base = String::new();
+    for segment in url.split_inclusive('/') {
+        if segment.contains('$') {
+            break;
+        }
+        base.push_str(segment);
+    }
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.url.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_line_as_pacman() {
+        assert_eq!(
+            parse_line("Server=https://a/"),
+            Some(Line::Directive("Server", Some("https://a/")))
+        );
+        assert_eq!(
+            parse_line("  Server =  https://a/$repo  # fast "),
+            Some(Line::Directive("Server", Some("https://a/$repo")))
+        );
+        assert_eq!(parse_line(" [core] "), Some(Line::Section("core")));
+        assert_eq!(parse_line("Color"), Some(Line::Directive("Color", None)));
+        assert_eq!(parse_line("   # comment"), None);
+        assert_eq!(parse_line(""), None);
+    }
+
+    #[test]
+    fn parse_crlf_and_inline_comments() {
+        let mirrorlist = parse("## header\r\nServer=https://a/$repo/os/$arch\r\nServer = https://b/$repo/os/$arch # b\r\n");
+        assert!(mirrorlist.problems.is_empty());
+        assert_eq!(
+            urls(&mirrorlist.entries),
+            ["https://a/$repo/os/$arch", "https://b/$repo/os/$arch"]
+        );
+        assert_eq!(mirrorlist.servers(), ["https://a/", "https://b/"]);
+        assert_eq!(mirrorlist.entries[1].line, 3);
+    }
+
+    #[test]
+    fn server_base_of_the_layouts() {
+        assert_eq!(
+            server_base("https://a/archlinux/$repo/os/$arch"),
+            "https://a/archlinux/"
+        );
+        assert_eq!(
+            server_base("https://a/archlinux/$arch/$repo"),
+            "https://a/archlinux/"
+        );
+        assert_eq!(server_base("https://a/archlinux/"), "https://a/archlinux/");
+        assert_eq!(server_base("https://a/archlinux"), "https://a/archlinux");
+    }
+
+    #[test]
+    fn bare_server_is_an_error() {
+        let mirrorlist = parse("Server\nServer =\nServer = https://a/$repo/os/$arch\n");
+        assert_eq!(mirrorlist.servers(), ["https://a/"]);
+        let problems: Vec<(usize, Severity)> = mirrorlist
+            .problems
+            .iter()
+            .map(|problem| (problem.line, problem.severity))
+            .collect();
+        assert_eq!(problems, [(1, Severity::Error), (2, Severity::Error)]);
+    }
+
+    #[test]
+    fn duplicates_refer_to_the_first_line() {
+        let mirrorlist = parse(
+            "Server = https://a/$repo/os/$arch\n\nServer = https://a/$arch/$repo\nServer = https://a/$repo/os/$arch\n",
+        );
+        assert_eq!(mirrorlist.entries.len(), 3);
+        let problems: Vec<(usize, &str)> = mirrorlist
+            .problems
+            .iter()
+            .map(|problem| (problem.line, problem.message.as_str()))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                (3, "duplicate of the server of the line 1"),
+                (4, "duplicate of the server of the line 1")
+            ]
+        );
+        assert!(mirrorlist
+            .problems
+            .iter()
+            .all(|problem| problem.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn cache_servers_are_not_servers() {
+        let mirrorlist = parse(
+            "CacheServer = http://cache/$repo/os/$arch\nServer = http://cache/$repo/os/$arch\n",
+        );
+        assert!(mirrorlist.problems.is_empty());
+        assert_eq!(mirrorlist.entries[0].directive, Directive::CacheServer);
+        assert_eq!(mirrorlist.servers(), ["http://cache/"]);
+    }
+
+    #[test]
+    fn disabled_servers() {
+        let mirrorlist = parse(
+            "## Server = https://prose/$repo/os/$arch\n##Server = https://prose/$repo/os/$arch\n\
+             #Server = https://a/$repo/os/$arch\n  # Server=https://b/$repo/os/$arch\n\
+             # Server list generated by reflector\n#CacheServer = http://cache/$repo\n",
+        );
+        assert!(mirrorlist.entries.is_empty());
+        assert!(mirrorlist.problems.is_empty());
+        assert_eq!(mirrorlist.disabled_servers(), ["https://a/", "https://b/"]);
+        assert_eq!(mirrorlist.disabled[0].line, 3);
+    }
+}
@@ -3,6 +3,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use crate::error::Error;
+use crate::painter::Painter;
 use html2text::{
     from_read_rich, from_read_with_decorator,
     render::text_renderer::{RichAnnotation, TaggedLine, TaggedLineElement, TextDecorator},
@@ -22,6 +23,7 @@ pub struct Article<'a> {
     link: &'a str,
     content: &'a str,
     date: &'a str,
+    painter: Painter,
 }
 
 impl<'a> Display for Article<'a> {
@@ -30,23 +32,23 @@ impl<'a> Display for Article<'a> {
             f,
             "{}{}{}{} {}{}{}{}\n{}{}{}{}\n\n{}{}{}",
             // date
-            Italic,
-            Fg(Magenta),
+            self.painter.paint(Italic),
+            self.painter.paint(Fg(Magenta)),
             self.date,
-            StyleReset,
+            self.painter.paint(StyleReset),
             // title
-            Bold,
-            Fg(Green),
+            self.painter.paint(Bold),
+            self.painter.paint(Fg(Green)),
             self.title,
-            StyleReset,
+            self.painter.paint(StyleReset),
             // link
-            Underline,
-            Fg(Blue),
+            self.painter.paint(Underline),
+            self.painter.paint(Fg(Blue)),
             self.link,
-            StyleReset,
+            self.painter.paint(StyleReset),
             //content
-            StyleReset,
-            Fg(Reset),
+            self.painter.paint(StyleReset),
+            self.painter.paint(Fg(Reset)),
             self.content
         )
     }
@@ -56,22 +58,25 @@ pub struct News<'a> {
     raw_html: String,
     arch_url: &'a str,
     last: Option<u8>,
+    painter: Painter,
 }
 
 #[derive(Debug)]
 struct ContentDecorator(Vec<String>);
 
 impl<'a> News<'a> {
-    pub fn new(raw_html: String, arch_url: &'a str, last: Option<u8>) -> Self {
+    pub fn new(raw_html: String, arch_url: &'a str, last: Option<u8>, painter: Painter) -> Self {
         News {
             raw_html,
             arch_url,
             last,
+            painter,
         }
     }
 
     pub fn parse(&mut self) -> Result<String, Error> {
-        let mut term_width = terminal_size()?.0 as usize;
+        // not in a terminal, e.g. piped, wrap at the ideal line length
+        let mut term_width = terminal_size().map_or(LINE_LENGTH, |(width, _)| width as usize);
         if term_width > LINE_LENGTH {
             term_width = LINE_LENGTH;
         }
@@ -104,6 +109,7 @@ impl<'a> News<'a> {
                 link: &val.1,
                 content: &contents[i],
                 date: &dates[i],
+                painter: self.painter,
             })
             .collect();
         if let Some(last) = self.last {
@@ -111,14 +117,14 @@ impl<'a> News<'a> {
         };
         let output = format!(
             "{}{}Latest News{}\n{}{}{}/news{}{}",
-            Bold,
-            Fg(Yellow),
-            StyleReset,
-            Underline,
-            Fg(Blue),
+            self.painter.paint(Bold),
+            self.painter.paint(Fg(Yellow)),
+            self.painter.paint(StyleReset),
+            self.painter.paint(Underline),
+            self.painter.paint(Fg(Blue)),
             self.arch_url,
-            StyleReset,
-            Fg(Reset)
+            self.painter.paint(StyleReset),
+            self.painter.paint(Fg(Reset))
         );
         let articles = articles.iter().fold(String::new(), |acc, article| {
             format!("{}\n{}", acc, article)
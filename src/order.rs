@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::painter::Level;
+use crate::thresholds::Thresholds;
+use crate::MirrorState;
+
+/// Analysis of the order of the mirrors: pacman tries the servers in
+/// order and only moves to the next one when a download fails, so a
+/// stale mirror at the top of the list is used whatever comes after it
+pub struct Order<'a> {
+    mirrors: &'a [MirrorState],
+    /// Why each mirror is not healthy, `None` when it is
+    problems: Vec<Option<&'static str>>,
+}
+
+impl<'a> Order<'a> {
+    pub fn new(mirrors: &'a [MirrorState], thresholds: &Thresholds) -> Self {
+        let problems = mirrors
+            .iter()
+            .map(|state| match state {
+                MirrorState::OutOfSync(_) => Some("out of sync"),
+                MirrorState::NotFound(_) => Some("not found in the mirror status list"),
+                MirrorState::Synced(mirror) if thresholds.level(mirror) == Level::Critical => {
+                    Some("beyond a critical threshold")
+                }
+                MirrorState::Synced(_) => None,
+            })
+            .collect();
+        Order { mirrors, problems }
+    }
+
+    /// Position, from 1, of the first healthy mirror
+    pub fn first_healthy(&self) -> Option<usize> {
+        self.problems
+            .iter()
+            .position(Option::is_none)
+            .map(|index| index + 1)
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        self.positioned_warnings()
+            .into_iter()
+            .map(|(_, warning)| warning)
+            .collect()
+    }
+
+    /// The warnings with the index of the mirror they are about, `None`
+    /// when about the whole list
+    pub fn positioned_warnings(&self) -> Vec<(Option<usize>, String)> {
+        let mut warnings = vec![];
+        if self.mirrors.is_empty() {
+            return warnings;
+        }
+        let last_healthy = match self.problems.iter().rposition(Option::is_none) {
+            Some(index) => index,
+            None => {
+                warnings.push((None, "none of the mirrors is healthy".to_string()));
+                return warnings;
+            }
+        };
+        if let Some(problem) = self.problems[0] {
+            let first_healthy = self.first_healthy().unwrap_or_default();
+            warnings.push((
+                Some(0),
+                format!(
+                    "pacman hits {} first but it is {}, the first healthy mirror {} is at position {}",
+                    self.mirrors[0].url(),
+                    problem,
+                    self.mirrors[first_healthy - 1].url(),
+                    first_healthy
+                ),
+            ));
+        }
+        // the first one is reported above
+        for (index, problem) in self.problems.iter().enumerate().take(last_healthy).skip(1) {
+            if let Some(problem) = problem {
+                warnings.push((
+                    Some(index),
+                    format!(
+                        "{} at position {} is {} and precedes healthy mirrors",
+                        self.mirrors[index].url(),
+                        index + 1,
+                        problem
+                    ),
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mirror;
+    use crate::Mirror;
+
+    fn good(name: &str) -> MirrorState {
+        MirrorState::Synced(Mirror {
+            url: format!("https://{}/", name),
+            ..mirror(0.5)
+        })
+    }
+
+    fn bad(name: &str) -> MirrorState {
+        MirrorState::OutOfSync(Mirror {
+            url: format!("https://{}/", name),
+            ..mirror(0.5)
+        })
+    }
+
+    fn warnings(mirrors: &[MirrorState]) -> Vec<(Option<usize>, String)> {
+        Order::new(mirrors, &Thresholds::default()).positioned_warnings()
+    }
+
+    fn first_healthy(mirrors: &[MirrorState]) -> Option<usize> {
+        Order::new(mirrors, &Thresholds::default()).first_healthy()
+    }
+
+    #[test]
+    fn healthy_order() {
+        let mirrors = [good("a"), good("b")];
+        assert!(warnings(&mirrors).is_empty());
+        assert_eq!(first_healthy(&mirrors), Some(1));
+        assert!(warnings(&[]).is_empty());
+        assert_eq!(first_healthy(&[]), None);
+    }
+
+    #[test]
+    fn bad_first_mirror() {
+        let mirrors = [bad("a"), bad("b"), good("c")];
+        assert_eq!(first_healthy(&mirrors), Some(3));
+        assert_eq!(
+            warnings(&mirrors),
+            [
+                (
+                    Some(0),
+                    "pacman hits https://a/ first but it is out of sync, the first healthy mirror https://c/ is at position 3"
+                        .to_string()
+                ),
+                (
+                    Some(1),
+                    "https://b/ at position 2 is out of sync and precedes healthy mirrors".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_mirror_between_good_ones() {
+        let critical = MirrorState::Synced(Mirror {
+            url: "https://b/".to_string(),
+            ..mirror(5.0)
+        });
+        let mirrors = [
+            good("a"),
+            critical,
+            MirrorState::NotFound("https://c/".to_string()),
+            good("d"),
+        ];
+        assert_eq!(first_healthy(&mirrors), Some(1));
+        assert_eq!(
+            warnings(&mirrors),
+            [
+                (
+                    Some(1),
+                    "https://b/ at position 2 is beyond a critical threshold and precedes healthy mirrors"
+                        .to_string()
+                ),
+                (
+                    Some(2),
+                    "https://c/ at position 3 is not found in the mirror status list and precedes healthy mirrors"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_mirrors_after_the_last_good_one() {
+        let mirrors = [good("a"), bad("b"), bad("c")];
+        assert!(warnings(&mirrors).is_empty());
+        assert_eq!(first_healthy(&mirrors), Some(1));
+    }
+
+    #[test]
+    fn no_healthy_mirror() {
+        let mirrors = [bad("a"), bad("b")];
+        assert_eq!(first_healthy(&mirrors), None);
+        assert_eq!(
+            warnings(&mirrors),
+            [(None, "none of the mirrors is healthy".to_string())]
+        );
+    }
+}
@@ -0,0 +1,264 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::mirrorlist::{
+    self, parse_disabled_line, parse_line, print_problems, server_base, Line, SERVER,
+};
+use std::fs;
+use std::path::Path;
+
+const OPTIONS_SECTION: &str = "options";
+const INCLUDE: &str = "Include";
+/// Limit of nested `Include` directives, as pacman's
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// A repository section of pacman.conf with its resolved servers
+#[derive(Debug, Clone)]
+pub struct Repository {
+    pub name: String,
+    pub servers: Vec<String>,
+    /// The commented-out servers
+    pub disabled: Vec<String>,
+}
+
+/// Parse pacman.conf and resolve, for every repository, its `Server`
+/// and `Include` directives, in the order pacman reads them.
+/// `CacheServer` entries, local caches, are not checked.
+pub fn parse_pacman_conf(path: &str) -> Result<Vec<Repository>, String> {
+    let conf = fs::read_to_string(path)
+        .map_err(|err| format!("an error occured while reading the file {}: {}", path, err))?;
+    let mut repositories: Vec<Repository> = vec![];
+    parse_conf(&conf, &mut repositories, 0);
+    repositories.retain(|repository| !repository.servers.is_empty());
+    if repositories.is_empty() {
+        Err(format!("no repository with a server found in {}", path))
+    } else {
+        Ok(repositories)
+    }
+}
+
+/// Read the lines of pacman.conf, or of a file included from its
+/// `[options]` section, which may declare repositories too. A section
+/// of an included file goes on after it, as with pacman.
+fn parse_conf(conf: &str, repositories: &mut Vec<Repository>, depth: usize) {
+    // after a commented-out section, e.g. the stock `#[custom]`, the
+    // commented-out servers are not the ones of the last repository
+    let mut in_disabled_section = false;
+    for line in conf.lines() {
+        let (key, value) = match parse_line(line) {
+            None => {
+                if is_disabled_section(line) {
+                    in_disabled_section = true;
+                } else if let (false, Some(repository), Some(url)) = (
+                    in_disabled_section,
+                    repositories.last_mut(),
+                    parse_disabled_line(line),
+                ) {
+                    repository.disabled.push(server_base(url));
+                }
+                continue;
+            }
+            Some(Line::Section(section)) => {
+                in_disabled_section = false;
+                repositories.push(Repository {
+                    name: section.to_string(),
+                    servers: vec![],
+                    disabled: vec![],
+                });
+                continue;
+            }
+            Some(Line::Directive(key, Some(value))) => (key, value),
+            Some(Line::Directive(_, None)) => continue,
+        };
+        let section = match repositories.last() {
+            Some(repository) => repository.name.clone(),
+            None => continue,
+        };
+        match key {
+            SERVER if section != OPTIONS_SECTION => {
+                if let Some(repository) = repositories.last_mut() {
+                    repository.servers.push(server_base(value));
+                }
+            }
+            INCLUDE => {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    eprintln!(
+                        "warning: Include = {} of [{}] skipped, more than {} nested includes",
+                        value, section, MAX_INCLUDE_DEPTH
+                    );
+                    continue;
+                }
+                let includes = expand(value);
+                if includes.is_empty() {
+                    eprintln!(
+                        "warning: Include = {} of [{}] matches no file, skipped",
+                        value, section
+                    );
+                }
+                for include in includes {
+                    let content = match fs::read_to_string(&include) {
+                        Ok(content) => content,
+                        Err(err) => {
+                            eprintln!(
+                                "warning: {} included by [{}] skipped, it cannot be read: {}",
+                                include, section, err
+                            );
+                            continue;
+                        }
+                    };
+                    if section == OPTIONS_SECTION {
+                        parse_conf(&content, repositories, depth + 1);
+                    } else if let Some(repository) = repositories.last_mut() {
+                        let mirrorlist = mirrorlist::parse(&content);
+                        print_problems(&include, &mirrorlist.problems);
+                        repository.servers.extend(mirrorlist.servers());
+                        repository.disabled.extend(mirrorlist.disabled_servers());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a line is a commented-out section header, e.g. `#[custom]`
+fn is_disabled_section(line: &str) -> bool {
+    match line.trim_start().strip_prefix('#') {
+        Some(line) if !line.starts_with('#') => {
+            matches!(parse_line(line), Some(Line::Section(_)))
+        }
+        _ => false,
+    }
+}
+
+/// Expand an `Include` path as pacman does with glob(3), the matches in
+/// alphabetical order. An invalid pattern is taken literally.
+fn expand(pattern: &str) -> Vec<String> {
+    match glob::glob(pattern) {
+        Ok(paths) => paths
+            .filter_map(Result::ok)
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        Err(_) if Path::new(pattern).exists() => vec![pattern.to_string()],
+        Err(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+    use std::process;
+
+    /// A fresh directory for the files of a test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("milcheck-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(repositories: &[Repository]) -> Vec<&str> {
+        repositories
+            .iter()
+            .map(|repository| repository.name.as_str())
+            .collect()
+    }
+
+    fn parse(conf: &str) -> Vec<Repository> {
+        let mut repositories = vec![];
+        parse_conf(conf, &mut repositories, 0);
+        repositories
+    }
+
+    #[test]
+    fn disabled_sections_have_no_disabled_servers() {
+        let repositories = parse(
+            "[core]\nServer = https://a/$repo/os/$arch\n#Server = https://b/$repo/os/$arch\n\n\
+             # An example of a custom package repository.  See the pacman manpage for\n\
+             # tips on creating your own repositories.\n\
+             #[custom]\n#SigLevel = Optional TrustAll\n#Server = file:///home/custompkgs\n\n\
+             [extra]\n#Server = https://c/$repo/os/$arch\n",
+        );
+        assert_eq!(repositories.len(), 2);
+        assert_eq!(repositories[0].servers, ["https://a/"]);
+        assert_eq!(repositories[0].disabled, ["https://b/"]);
+        assert_eq!(repositories[1].disabled, ["https://c/"]);
+    }
+
+    #[test]
+    fn servers_of_options_are_ignored() {
+        let repositories = parse("[options]\nServer = https://a/$repo/os/$arch\n[core]\nServer = https://b/$repo/os/$arch\n");
+        assert_eq!(names(&repositories), ["options", "core"]);
+        assert!(repositories[0].servers.is_empty());
+        assert_eq!(repositories[1].servers, ["https://b/"]);
+    }
+
+    #[test]
+    fn include_globs_in_alphabetical_order() {
+        let dir = temp_dir("glob");
+        fs::write(
+            dir.join("mirrorlist-b"),
+            "Server = https://b/$repo/os/$arch\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("mirrorlist-a"),
+            "Server = https://a/$repo/os/$arch\n#Server = https://c/$repo/os/$arch\n",
+        )
+        .unwrap();
+        fs::write(dir.join("other"), "Server = https://d/$repo/os/$arch\n").unwrap();
+        let repositories = parse(&format!(
+            "[core]\nInclude = {}\n",
+            dir.join("mirrorlist-*").display()
+        ));
+        assert_eq!(repositories[0].servers, ["https://a/", "https://b/"]);
+        assert_eq!(repositories[0].disabled, ["https://c/"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn includes_of_options_declare_repositories() {
+        let dir = temp_dir("options");
+        let custom = dir.join("custom.conf");
+        fs::write(
+            &custom,
+            "[custom]\nServer = https://a/$repo/os/$arch\nServer = https://b/$repo/os/$arch\n",
+        )
+        .unwrap();
+        let repositories = parse(&format!(
+            "[options]\nInclude = {}\nServer = https://c/$repo/os/$arch\n",
+            custom.display()
+        ));
+        assert_eq!(names(&repositories), ["options", "custom"]);
+        // the section of the included file goes on after it
+        assert_eq!(
+            repositories[1].servers,
+            ["https://a/", "https://b/", "https://c/"]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn nested_includes_are_cut_off() {
+        let dir = temp_dir("depth");
+        let conf = dir.join("loop.conf");
+        fs::write(
+            &conf,
+            format!(
+                "[options]\nInclude = {}\n[loop]\nServer = https://a/$repo/os/$arch\n",
+                conf.display()
+            ),
+        )
+        .unwrap();
+        let repositories = parse(&format!("[options]\nInclude = {}\n", conf.display()));
+        let loops = repositories
+            .iter()
+            .filter(|repository| repository.name == "loop")
+            .count();
+        assert_eq!(loops, MAX_INCLUDE_DEPTH);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt::Display;
+use termion::color::{Fg, Red, Yellow};
+
+/// Markers of the levels in plain output, where colors are not available
+const WARNING_MARKER: &str = "~";
+const CRITICAL_MARKER: &str = "!";
+
+/// How bad a value is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Normal,
+    Warning,
+    Critical,
+}
+
+/// Gives the terminal escape sequences (colors, styles), or nothing
+/// when the output must stay plain text
+#[derive(Debug, Clone, Copy)]
+pub struct Painter(bool);
+
+impl Painter {
+    pub fn new(enabled: bool) -> Self {
+        Painter(enabled)
+    }
+
+    pub fn paint<T: Display>(&self, sequence: T) -> String {
+        if self.0 {
+            sequence.to_string()
+        } else {
+            "".to_string()
+        }
+    }
+
+    /// Color of a value of the given level
+    pub fn level(&self, level: Level) -> String {
+        match level {
+            Level::Normal => "".to_string(),
+            Level::Warning => self.paint(Fg(Yellow)),
+            Level::Critical => self.paint(Fg(Red)),
+        }
+    }
+
+    /// Textual marker of the level, appended to the value in plain
+    /// output so that the level is not conveyed by the color alone
+    pub fn marker(&self, level: Level) -> &'static str {
+        match level {
+            _ if self.0 => "",
+            Level::Normal => " ",
+            Level::Warning => WARNING_MARKER,
+            Level::Critical => CRITICAL_MARKER,
+        }
+    }
+}
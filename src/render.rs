@@ -3,7 +3,8 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use crate::error::Error;
-use crate::event::{Event, Events};
+use crate::event::{Event, Events, TICK_RATE};
+use crate::painter::Painter;
 use std::io::{self, Write};
 use std::iter::Cycle;
 use std::process;
@@ -13,22 +14,31 @@ use std::thread::{self, JoinHandle};
 use std::time::Instant;
 use termion::clear::AfterCursor;
 use termion::color::{Fg, Reset as ColorReset, Yellow};
-use termion::cursor::{DetectCursorPos, Goto, Hide, Restore, Save, Show};
+use termion::cursor::{Hide, Restore, Save, Show};
+use termion::is_tty;
 use termion::raw::IntoRawMode;
 use termion::style::{Italic, Reset};
 
 const SPINNER_RATE: u128 = 40;
 
-pub struct Render(Option<JoinHandle<Result<(), Error>>>);
+/// Draw a spinner with the progress states on stderr, leaving stdout to
+/// the output. When disabled, e.g. not in a terminal, the states are
+/// only consumed. The painter drops its styles when colors are off.
+pub struct Render(Option<JoinHandle<Result<(), Error>>>, bool, Painter);
 
 impl Render {
-    pub fn new() -> Render {
-        Render(None)
+    pub fn new(enabled: bool, painter: Painter) -> Render {
+        Render(None, enabled, painter)
     }
 
     pub fn run(&mut self, rx: Receiver<&'static str>) {
+        let (enabled, painter) = (self.1, self.2);
         let handle = thread::spawn(move || -> Result<(), Error> {
-            draw(rx)?;
+            if enabled {
+                draw(rx, &painter)?;
+            } else {
+                rx.iter().for_each(drop);
+            }
             Ok(())
         });
         self.0 = Some(handle);
@@ -43,12 +53,6 @@ impl Render {
     }
 }
 
-impl Default for Render {
-    fn default() -> Self {
-        Render::new()
-    }
-}
-
 struct Spinner<'a> {
     time: Instant,
     step: &'a str,
@@ -91,27 +95,38 @@ impl<'a> Iterator for Spinner<'a> {
     }
 }
 
-fn draw(rx: Receiver<&'static str>) -> Result<(), Error> {
-    let mut stdout = io::stdout().into_raw_mode()?;
-    let (init_a, init_b) = stdout.cursor_pos()?;
-    let events = Events::new();
-    write!(stdout, "{}{}", Save, Hide)?;
+fn draw(rx: Receiver<&'static str>, painter: &Painter) -> Result<(), Error> {
+    let mut stderr = io::stderr();
+    // termion switches the terminal to raw mode through stdout, so keys
+    // can only be read when both stdin and stdout are the terminal
+    let raw_mode = if is_tty(&io::stdin()) && is_tty(&io::stdout()) {
+        Some(io::stdout().into_raw_mode()?)
+    } else {
+        None
+    };
+    let events = raw_mode.as_ref().map(|_| Events::new());
+    write!(stderr, "{}", Save)?;
+    // without raw mode, ^C kills the process before the cursor is shown
+    if raw_mode.is_some() {
+        write!(stderr, "{}", Hide)?;
+    }
     let mut tmp_state = "";
+    let (yellow, reset) = (painter.paint(Fg(Yellow)), painter.paint(Fg(ColorReset)));
     let steps = [
         " ----".to_string(),
-        format!("{}c{}----", Fg(Yellow), Fg(ColorReset)),
-        format!("{}C{}----", Fg(Yellow), Fg(ColorReset)),
-        format!(" {}C{}---", Fg(Yellow), Fg(ColorReset)),
-        format!(" {}c{}---", Fg(Yellow), Fg(ColorReset)),
-        format!(" {}C{}---", Fg(Yellow), Fg(ColorReset)),
-        format!("  {}C{}--", Fg(Yellow), Fg(ColorReset)),
-        format!("  {}c{}--", Fg(Yellow), Fg(ColorReset)),
-        format!("  {}C{}--", Fg(Yellow), Fg(ColorReset)),
-        format!("   {}C{}-", Fg(Yellow), Fg(ColorReset)),
-        format!("   {}c{}-", Fg(Yellow), Fg(ColorReset)),
-        format!("   {}C{}-", Fg(Yellow), Fg(ColorReset)),
-        format!("    {}C{}", Fg(Yellow), Fg(ColorReset)),
-        format!("    {}c{}", Fg(Yellow), Fg(ColorReset)),
+        format!("{}c{}----", yellow, reset),
+        format!("{}C{}----", yellow, reset),
+        format!(" {}C{}---", yellow, reset),
+        format!(" {}c{}---", yellow, reset),
+        format!(" {}C{}---", yellow, reset),
+        format!("  {}C{}--", yellow, reset),
+        format!("  {}c{}--", yellow, reset),
+        format!("  {}C{}--", yellow, reset),
+        format!("   {}C{}-", yellow, reset),
+        format!("   {}c{}-", yellow, reset),
+        format!("   {}C{}-", yellow, reset),
+        format!("    {}C{}", yellow, reset),
+        format!("    {}c{}", yellow, reset),
     ];
     let mut spinner = Spinner::new(&steps);
     loop {
@@ -126,23 +141,33 @@ fn draw(rx: Receiver<&'static str>) -> Result<(), Error> {
                 }
             }
         }
-        write!(stdout, "{}{}", Goto(init_a, init_b), AfterCursor)?;
+        write!(stderr, "{}{}", Restore, AfterCursor)?;
         if let Some(value) = spinner.next() {
-            write!(stdout, "{}  ", value)?;
+            write!(stderr, "{}  ", value)?;
         }
-        write!(stdout, "{}{}{}", Italic, tmp_state, Reset)?;
-        stdout.flush()?;
-        if let Event::Input(key) = events.next()? {
-            if Events::is_exit_key(key) {
-                write!(stdout, "{}{}{}", Show, Restore, AfterCursor)?;
-                stdout.flush()?;
-                drop(stdout);
-                process::exit(1);
+        write!(
+            stderr,
+            "{}{}{}",
+            painter.paint(Italic),
+            tmp_state,
+            painter.paint(Reset)
+        )?;
+        stderr.flush()?;
+        match &events {
+            Some(events) => {
+                if let Event::Input(key) = events.next()? {
+                    if Events::is_exit_key(key) {
+                        write!(stderr, "{}{}{}", Show, Restore, AfterCursor)?;
+                        stderr.flush()?;
+                        drop(raw_mode);
+                        process::exit(1);
+                    }
+                }
             }
+            None => thread::sleep(TICK_RATE),
         }
     }
-    // events.finish()?;
-    write!(stdout, "{}{}{}", Restore, AfterCursor, Show)?;
-    stdout.flush()?;
+    write!(stderr, "{}{}{}", Restore, AfterCursor, Show)?;
+    stderr.flush()?;
     Ok(())
 }
@@ -0,0 +1,316 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::error::Error;
+use crate::lint::Finding;
+use crate::order::Order;
+use crate::thresholds::Thresholds;
+use crate::{MirrorState, Mirrorlist};
+use serde::Serialize;
+
+// Bump this whenever a field is renamed, removed or changes meaning.
+// Adding a new field is not a breaking change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+const SYNCED: &str = "synced";
+const OUT_OF_SYNC: &str = "out_of_sync";
+const NOT_FOUND: &str = "not_found";
+
+#[derive(Serialize, Debug)]
+pub struct Report<'a> {
+    version: u32,
+    mirrorlists: Vec<MirrorlistReport<'a>>,
+}
+
+/// The findings of the lint of a mirrorlist
+#[derive(Serialize, Debug)]
+pub struct LintReport<'a> {
+    version: u32,
+    path: &'a str,
+    findings: &'a [Finding],
+}
+
+#[derive(Serialize, Debug)]
+struct MirrorlistReport<'a> {
+    path: &'a str,
+    repository: Option<&'a str>,
+    /// A repository none of whose servers is in the status list
+    unofficial: bool,
+    mirrors: Vec<MirrorReport<'a>>,
+    /// The commented-out mirrors, when evaluated
+    disabled: Vec<MirrorReport<'a>>,
+    /// Position, from 1, of the first healthy mirror in the whole list
+    first_healthy: Option<usize>,
+    /// Problems of the order of the whole list
+    warnings: Vec<String>,
+    suggestions: Vec<SuggestionReport<'a>>,
+    diagnoses: Vec<DiagnosisReport<'a>>,
+}
+
+#[derive(Serialize, Debug)]
+struct DiagnosisReport<'a> {
+    url: &'a str,
+    host: &'a str,
+    closest: &'a [String],
+}
+
+#[derive(Serialize, Debug)]
+struct SuggestionReport<'a> {
+    url: &'a str,
+    country_code: &'a str,
+    protocol: &'a str,
+    replacements: Vec<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+struct MirrorReport<'a> {
+    state: &'static str,
+    url: &'a str,
+    protocol: Option<&'a str>,
+    country: Option<&'a str>,
+    country_code: Option<&'a str>,
+    completion: Option<f64>,
+    delay: Option<u32>,
+    duration_avg: Option<f64>,
+    duration_stddev: Option<f64>,
+    score: Option<f64>,
+    /// Unix time in seconds
+    last_sync: Option<u64>,
+    active: Option<bool>,
+    isos: Option<bool>,
+    ipv4: Option<bool>,
+    ipv6: Option<bool>,
+    details: Option<&'a str>,
+}
+
+impl<'a> From<&'a MirrorState> for MirrorReport<'a> {
+    fn from(mirror_state: &'a MirrorState) -> Self {
+        let (state, mirror) = match mirror_state {
+            MirrorState::NotFound(server) => {
+                return MirrorReport {
+                    state: NOT_FOUND,
+                    url: server,
+                    protocol: None,
+                    country: None,
+                    country_code: None,
+                    completion: None,
+                    delay: None,
+                    duration_avg: None,
+                    duration_stddev: None,
+                    score: None,
+                    last_sync: None,
+                    active: None,
+                    isos: None,
+                    ipv4: None,
+                    ipv6: None,
+                    details: None,
+                }
+            }
+            MirrorState::OutOfSync(mirror) => (OUT_OF_SYNC, mirror),
+            MirrorState::Synced(mirror) => (SYNCED, mirror),
+        };
+        MirrorReport {
+            state,
+            url: &mirror.url,
+            protocol: Some(&mirror.protocol),
+            country: Some(&mirror.country),
+            country_code: Some(&mirror.country_code),
+            completion: mirror.completion,
+            delay: mirror.delay,
+            duration_avg: mirror.duration_avg,
+            duration_stddev: mirror.duration_stddev,
+            score: mirror.score,
+            last_sync: mirror.last_sync,
+            active: Some(mirror.active),
+            isos: Some(mirror.isos),
+            ipv4: Some(mirror.ipv4),
+            ipv6: Some(mirror.ipv6),
+            details: Some(&mirror.details),
+        }
+    }
+}
+
+impl<'a> Report<'a> {
+    /// `lists` are the printed mirrorlists, possibly filtered and sorted,
+    /// `whole_lists` the same ones as read, for the order analysis
+    pub fn new(
+        lists: &'a [Mirrorlist],
+        whole_lists: &[Mirrorlist],
+        thresholds: &Thresholds,
+    ) -> Self {
+        Report {
+            version: SCHEMA_VERSION,
+            mirrorlists: lists
+                .iter()
+                .zip(whole_lists)
+                .map(|(list, whole_list)| {
+                    // the order of an unofficial repository is not checked
+                    let ordered: &[MirrorState] = if whole_list.unofficial {
+                        &[]
+                    } else {
+                        &whole_list.mirrors
+                    };
+                    let order = Order::new(ordered, thresholds);
+                    MirrorlistReport {
+                        path: &list.path,
+                        repository: list.repository.as_deref(),
+                        unofficial: list.unofficial,
+                        mirrors: list.mirrors.iter().map(MirrorReport::from).collect(),
+                        disabled: list.disabled.iter().map(MirrorReport::from).collect(),
+                        first_healthy: order.first_healthy(),
+                        warnings: order.warnings(),
+                        suggestions: list
+                            .suggestions
+                            .iter()
+                            .map(|suggestion| SuggestionReport {
+                                url: &suggestion.url,
+                                country_code: &suggestion.country_code,
+                                protocol: &suggestion.protocol,
+                                replacements: suggestion
+                                    .mirrors
+                                    .iter()
+                                    .map(|mirror| mirror.url.as_str())
+                                    .collect(),
+                            })
+                            .collect(),
+                        diagnoses: list
+                            .diagnoses
+                            .iter()
+                            .map(|diagnosis| DiagnosisReport {
+                                url: &diagnosis.url,
+                                host: &diagnosis.host,
+                                closest: &diagnosis.closest,
+                            })
+                            .collect(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| Error::new(format!("json serialization failed: {}", err)))
+    }
+}
+
+impl<'a> LintReport<'a> {
+    pub fn new(path: &'a str, findings: &'a [Finding]) -> Self {
+        LintReport {
+            version: SCHEMA_VERSION,
+            path,
+            findings,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| Error::new(format!("json serialization failed: {}", err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::Diagnosis;
+    use crate::suggest::Suggestion;
+    use crate::tests::mirror;
+    use crate::Mirror;
+    use serde_json::Value;
+
+    fn keys(value: &Value) -> Vec<&str> {
+        value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect()
+    }
+
+    const MIRROR_KEYS: [&str; 16] = [
+        "active",
+        "completion",
+        "country",
+        "country_code",
+        "delay",
+        "details",
+        "duration_avg",
+        "duration_stddev",
+        "ipv4",
+        "ipv6",
+        "isos",
+        "last_sync",
+        "protocol",
+        "score",
+        "state",
+        "url",
+    ];
+
+    #[test]
+    fn schema() {
+        let lists = [Mirrorlist {
+            path: "/etc/pacman.d/mirrorlist".to_string(),
+            repository: None,
+            mirrors: vec![
+                MirrorState::Synced(mirror(0.5)),
+                MirrorState::OutOfSync(Mirror {
+                    url: "https://b.example.org/".to_string(),
+                    ..mirror(0.5)
+                }),
+                MirrorState::NotFound("https://c.example.org/".to_string()),
+            ],
+            suggestions: vec![Suggestion {
+                url: "https://b.example.org/".to_string(),
+                country_code: "FR".to_string(),
+                protocol: "https".to_string(),
+                mirrors: vec![mirror(0.5)],
+            }],
+            diagnoses: vec![Diagnosis {
+                url: "https://c.example.org/".to_string(),
+                host: "c.example.org".to_string(),
+                closest: vec![],
+            }],
+            disabled: vec![],
+            unofficial: false,
+        }];
+        let json = Report::new(&lists, &lists, &Thresholds::default())
+            .to_json()
+            .unwrap();
+        let report: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(keys(&report), ["mirrorlists", "version"]);
+        assert_eq!(report["version"], 1);
+
+        let list = &report["mirrorlists"][0];
+        assert_eq!(
+            keys(list),
+            [
+                "diagnoses",
+                "disabled",
+                "first_healthy",
+                "mirrors",
+                "path",
+                "repository",
+                "suggestions",
+                "unofficial",
+                "warnings"
+            ]
+        );
+        assert_eq!(list["first_healthy"], 1);
+        assert_eq!(
+            keys(&list["suggestions"][0]),
+            ["country_code", "protocol", "replacements", "url"]
+        );
+        assert_eq!(keys(&list["diagnoses"][0]), ["closest", "host", "url"]);
+
+        let mirrors = list["mirrors"].as_array().unwrap();
+        let states: Vec<&Value> = mirrors.iter().map(|mirror| &mirror["state"]).collect();
+        assert_eq!(states, ["synced", "out_of_sync", "not_found"]);
+        for mirror in mirrors {
+            assert_eq!(keys(mirror), MIRROR_KEYS);
+        }
+        assert_eq!(mirrors[0]["completion"], 100.0);
+        assert_eq!(mirrors[2]["url"], "https://c.example.org/");
+        assert!(mirrors[2]["score"].is_null());
+    }
+}
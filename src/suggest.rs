@@ -0,0 +1,203 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::painter::Level;
+use crate::thresholds::Thresholds;
+use crate::{JsonResponse, Mirror, MirrorState};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Healthy mirrors of the status list able to replace an out of sync or
+/// unknown mirror of a mirrorlist
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    /// The replaced mirror
+    pub url: String,
+    pub country_code: String,
+    pub protocol: String,
+    /// Best first
+    pub mirrors: Vec<Mirror>,
+}
+
+/// Suggest, for every out of sync or unknown mirror, up to `count`
+/// synced mirrors of the same country and protocol, ranked by score.
+/// The country of an unknown mirror is the most common one of the
+/// mirrorlist.
+pub fn suggest(
+    mirrors: &[MirrorState],
+    json: &JsonResponse,
+    thresholds: &Thresholds,
+    count: usize,
+) -> Vec<Suggestion> {
+    if count == 0 {
+        return vec![];
+    }
+    let mut candidates: Vec<Mirror> = json
+        .urls
+        .iter()
+        .filter(|mirror| mirror.is_synced(json.cutoff))
+        .map(Mirror::from)
+        .filter(|mirror| thresholds.level(mirror) != Level::Critical)
+        // the mirrors already in the list are no replacement
+        .filter(|mirror| !mirrors.iter().any(|state| state.url() == mirror.url))
+        .collect();
+    candidates.sort_by(|a, b| match (a.score, b.score) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    });
+    let main_country = main_country(mirrors);
+    mirrors
+        .iter()
+        .filter_map(|state| {
+            let (country_code, protocol) = match state {
+                MirrorState::Synced(_) => return None,
+                MirrorState::OutOfSync(mirror) => (&mirror.country_code, mirror.protocol.as_str()),
+                MirrorState::NotFound(server) => (main_country?, server.split_once("://")?.0),
+            };
+            let mirrors: Vec<Mirror> = candidates
+                .iter()
+                .filter(|mirror| {
+                    &mirror.country_code == country_code && mirror.protocol == protocol
+                })
+                .take(count)
+                .cloned()
+                .collect();
+            if mirrors.is_empty() {
+                return None;
+            }
+            Some(Suggestion {
+                url: state.url().to_string(),
+                country_code: country_code.to_string(),
+                protocol: protocol.to_string(),
+                mirrors,
+            })
+        })
+        .collect()
+}
+
+/// The most common country code of the mirrors found in the status
+/// list, the first one wins a tie
+fn main_country(mirrors: &[MirrorState]) -> Option<&String> {
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+    let mut countries = vec![];
+    for state in mirrors {
+        if let MirrorState::Synced(mirror) | MirrorState::OutOfSync(mirror) = state {
+            if mirror.country_code.is_empty() {
+                continue;
+            }
+            let count = counts.entry(&mirror.country_code).or_insert(0);
+            if *count == 0 {
+                countries.push(&mirror.country_code);
+            }
+            *count += 1;
+        }
+    }
+    countries
+        .into_iter()
+        .rev()
+        .max_by_key(|country| counts[country])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{json_mirror, json_response};
+    use crate::JsonMirror;
+
+    fn synced(url: &str, country_code: &str) -> MirrorState {
+        MirrorState::Synced(Mirror::from(&json_mirror(url, country_code, Some(600))))
+    }
+
+    fn out_of_sync(url: &str, country_code: &str) -> MirrorState {
+        MirrorState::OutOfSync(Mirror::from(&json_mirror(url, country_code, None)))
+    }
+
+    fn scored(url: &str, country_code: &str, score: f64) -> JsonMirror {
+        JsonMirror {
+            score: Some(score),
+            ..json_mirror(url, country_code, Some(600))
+        }
+    }
+
+    fn urls(suggestion: &Suggestion) -> Vec<&str> {
+        suggestion
+            .mirrors
+            .iter()
+            .map(|mirror| mirror.url.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn main_country_first_wins_a_tie() {
+        let mirrors = [
+            synced("https://a/", "FR"),
+            MirrorState::NotFound("https://b/".to_string()),
+            out_of_sync("https://c/", "DE"),
+            synced("https://d/", "DE"),
+            synced("https://e/", "FR"),
+            synced("https://f/", ""),
+        ];
+        assert_eq!(main_country(&mirrors).map(String::as_str), Some("FR"));
+        assert_eq!(main_country(&mirrors[3..5]).map(String::as_str), Some("DE"));
+        assert_eq!(main_country(&mirrors[5..]), None);
+    }
+
+    #[test]
+    fn best_scores_of_the_same_country_and_protocol() {
+        let json = json_response(vec![
+            scored("https://a/", "FR", 1.5),
+            scored("https://b/", "FR", 0.2),
+            scored("https://c/", "DE", 0.1),
+            scored("http://d/", "FR", 0.1),
+            scored("https://e/", "FR", 0.8),
+        ]);
+        let mirrors = [out_of_sync("https://x/", "FR")];
+        let suggestions = suggest(&mirrors, &json, &Thresholds::default(), 2);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].url, "https://x/");
+        assert_eq!(urls(&suggestions[0]), ["https://b/", "https://e/"]);
+    }
+
+    #[test]
+    fn mirrors_of_the_list_are_no_replacement() {
+        let json = json_response(vec![
+            scored("https://a/", "FR", 0.2),
+            scored("https://b/", "FR", 0.5),
+            json_mirror("https://x/", "FR", None),
+        ]);
+        let mirrors = [out_of_sync("https://x/", "FR"), synced("https://a/", "FR")];
+        let suggestions = suggest(&mirrors, &json, &Thresholds::default(), 3);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(urls(&suggestions[0]), ["https://b/"]);
+    }
+
+    #[test]
+    fn not_found_servers_get_the_main_country() {
+        let json = json_response(vec![
+            json_mirror("https://a/", "FR", Some(600)),
+            json_mirror("http://b/", "FR", Some(600)),
+            json_mirror("http://c/", "DE", Some(600)),
+        ]);
+        let mirrors = [
+            MirrorState::NotFound("http://z/".to_string()),
+            synced("https://a/", "FR"),
+        ];
+        let suggestions = suggest(&mirrors, &json, &Thresholds::default(), 3);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].country_code, "FR");
+        assert_eq!(suggestions[0].protocol, "http");
+        assert_eq!(urls(&suggestions[0]), ["http://b/"]);
+        // without a country to go by
+        let mirrors = [MirrorState::NotFound("http://z/".to_string())];
+        assert!(suggest(&mirrors, &json, &Thresholds::default(), 3).is_empty());
+    }
+
+    #[test]
+    fn no_suggestion_asked() {
+        let json = json_response(vec![json_mirror("https://a/", "FR", Some(600))]);
+        let mirrors = [out_of_sync("https://x/", "FR")];
+        assert_eq!(suggest(&mirrors, &json, &Thresholds::default(), 1).len(), 1);
+        assert!(suggest(&mirrors, &json, &Thresholds::default(), 0).is_empty());
+    }
+}
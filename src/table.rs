@@ -0,0 +1,625 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::painter::{Level, Painter};
+use crate::thresholds::Thresholds;
+use crate::MirrorState;
+use clap::ValueEnum;
+use serde::Deserialize;
+use termion::color::{Fg, Green, Red, Yellow};
+use termion::style::{Bold, Reset};
+
+const OK: &str = "Ok";
+const NOT_FOUND: &str = "Not found!";
+const OUT_OF_SYNC: &str = "Out of sync!";
+const YES: &str = "yes";
+const NO: &str = "no";
+const ELLIPSIS: &str = "…";
+/// Width under which the urls are not truncated further
+const MIN_TRUNCATED_WIDTH: usize = 20;
+/// Width of the values of a card under which they go below the labels
+const MIN_CARD_VALUE_WIDTH: usize = 12;
+
+/// The columns printed by default
+pub const COLUMNS: [Column; 9] = [
+    Column::State,
+    Column::Url,
+    Column::Protocol,
+    Column::Country,
+    Column::Completion,
+    Column::Delay,
+    Column::DurationAvg,
+    Column::DurationStddev,
+    Column::Score,
+];
+
+/// The columns printed in wide mode
+pub const WIDE_COLUMNS: [Column; 15] = [
+    Column::State,
+    Column::Url,
+    Column::Protocol,
+    Column::Country,
+    Column::Completion,
+    Column::Delay,
+    Column::DurationAvg,
+    Column::DurationStddev,
+    Column::Score,
+    Column::LastSync,
+    Column::Active,
+    Column::Isos,
+    Column::Ipv4,
+    Column::Ipv6,
+    Column::Details,
+];
+
+/// A column of the mirrors table
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    State,
+    Url,
+    Protocol,
+    Country,
+    /// Percentage of the checks the mirror was synced
+    Completion,
+    /// Time since the last sync (hh:mm)
+    Delay,
+    /// Average time of a check (s)
+    DurationAvg,
+    /// Standard deviation of the time of a check (s)
+    DurationStddev,
+    Score,
+    /// Age of the last sync
+    LastSync,
+    Active,
+    /// Whether the mirror hosts the installation ISOs
+    Isos,
+    Ipv4,
+    Ipv6,
+    /// Link to the mirror page of archlinux.org
+    Details,
+}
+
+impl Column {
+    fn header(&self) -> &'static str {
+        match self {
+            Column::State => "State",
+            Column::Url => "Url",
+            Column::Protocol => "Proto",
+            Column::Country => "Country",
+            Column::Completion => "Comp%",
+            Column::Delay => "Delay",
+            Column::DurationAvg => "Avg",
+            Column::DurationStddev => "Dev",
+            Column::Score => "Score",
+            Column::LastSync => "Last sync",
+            Column::Active => "Active",
+            Column::Isos => "ISOs",
+            Column::Ipv4 => "IPv4",
+            Column::Ipv6 => "IPv6",
+            Column::Details => "Details",
+        }
+    }
+
+    fn is_left_aligned(&self) -> bool {
+        matches!(
+            self,
+            Column::Url
+                | Column::Protocol
+                | Column::Country
+                | Column::Active
+                | Column::Isos
+                | Column::Ipv4
+                | Column::Ipv6
+                | Column::Details
+        )
+    }
+
+    fn is_truncatable(&self) -> bool {
+        matches!(self, Column::Url | Column::Details)
+    }
+
+    /// Priority of the column to stay in a table too wide for the
+    /// terminal, the lowest is dropped first, `None` when it cannot be
+    /// dropped
+    fn priority(&self) -> Option<u8> {
+        match self {
+            Column::State | Column::Url => None,
+            Column::Delay => Some(12),
+            Column::Score => Some(11),
+            Column::Country => Some(10),
+            Column::Completion => Some(9),
+            Column::Protocol => Some(8),
+            Column::LastSync => Some(7),
+            Column::DurationAvg => Some(6),
+            Column::DurationStddev => Some(5),
+            Column::Ipv6 => Some(4),
+            Column::Ipv4 => Some(3),
+            Column::Active => Some(2),
+            Column::Isos => Some(1),
+            Column::Details => Some(0),
+        }
+    }
+
+    /// Spaces separating the column from the previous one, the durations
+    /// are set apart
+    fn gap(&self) -> usize {
+        match self {
+            Column::DurationAvg | Column::DurationStddev => 2,
+            _ => 1,
+        }
+    }
+
+    fn cell(&self, state: &MirrorState, painter: &Painter, thresholds: &Thresholds) -> Cell {
+        let plain = |text: &str| Cell::new(text.to_string(), "".to_string());
+        let mirror = match state {
+            MirrorState::Synced(mirror) | MirrorState::OutOfSync(mirror) => mirror,
+            MirrorState::NotFound(server) => {
+                return match self {
+                    Column::State => state_cell(state, painter),
+                    Column::Url => plain(server),
+                    _ => plain(""),
+                };
+            }
+        };
+        let level = |level: Level, text: String| {
+            Cell::new(text + painter.marker(level), painter.level(level))
+        };
+        match self {
+            Column::State => state_cell(state, painter),
+            Column::Url => plain(&mirror.url),
+            Column::Protocol => plain(&mirror.protocol),
+            Column::Country => plain(&mirror.country),
+            Column::Completion => level(thresholds.completion(mirror), mirror.completion_to_str()),
+            Column::Delay => level(thresholds.delay(mirror), mirror.delay_to_str()),
+            Column::DurationAvg => plain(&mirror.duration_avg_to_str()),
+            Column::DurationStddev => plain(&mirror.duration_stddev_to_str()),
+            Column::Score => level(thresholds.score(mirror), mirror.score_to_str()),
+            Column::LastSync => plain(&mirror.last_sync_to_str()),
+            Column::Active => plain(yes_no(mirror.active)),
+            Column::Isos => plain(yes_no(mirror.isos)),
+            Column::Ipv4 => plain(yes_no(mirror.ipv4)),
+            Column::Ipv6 => plain(yes_no(mirror.ipv6)),
+            Column::Details => plain(&mirror.details),
+        }
+    }
+}
+
+/// A value of the table with its escape sequences
+#[derive(Clone)]
+struct Cell {
+    text: String,
+    style: String,
+}
+
+impl Cell {
+    fn new(text: String, style: String) -> Self {
+        Cell { text, style }
+    }
+
+    fn width(&self) -> usize {
+        self.text.chars().count()
+    }
+}
+
+fn state_cell(state: &MirrorState, painter: &Painter) -> Cell {
+    let (text, color) = match state {
+        MirrorState::Synced(_) => (OK, painter.paint(Fg(Green))),
+        MirrorState::OutOfSync(_) => (OUT_OF_SYNC, painter.paint(Fg(Red))),
+        MirrorState::NotFound(_) => (NOT_FOUND, painter.paint(Fg(Yellow))),
+    };
+    Cell::new(text.to_string(), painter.paint(Bold) + &color)
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        YES
+    } else {
+        NO
+    }
+}
+
+/// The cells of the table with the width of its columns
+#[derive(Clone)]
+struct Table {
+    columns: Vec<Column>,
+    headers: Vec<Cell>,
+    rows: Vec<Vec<Cell>>,
+    widths: Vec<usize>,
+}
+
+impl Table {
+    fn new(
+        mirrors: &[MirrorState],
+        columns: &[Column],
+        painter: &Painter,
+        thresholds: &Thresholds,
+    ) -> Self {
+        let headers = columns
+            .iter()
+            .map(|column| Cell::new(column.header().to_string(), "".to_string()))
+            .collect();
+        let rows = mirrors
+            .iter()
+            .map(|state| {
+                columns
+                    .iter()
+                    .map(|column| column.cell(state, painter, thresholds))
+                    .collect()
+            })
+            .collect();
+        let mut table = Table {
+            columns: columns.to_vec(),
+            headers,
+            rows,
+            widths: vec![],
+        };
+        table.widths = table.natural_widths();
+        table
+    }
+
+    /// Width of the columns fitting their whole values
+    fn natural_widths(&self) -> Vec<usize> {
+        (0..self.columns.len())
+            .map(|i| {
+                self.rows
+                    .iter()
+                    .map(|row| row[i].width())
+                    .chain([self.headers[i].width()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    fn total_width(&self) -> usize {
+        self.columns
+            .iter()
+            .skip(1)
+            .map(|column| column.gap())
+            .chain(self.widths.iter().copied())
+            .sum()
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.columns.remove(index);
+        self.headers.remove(index);
+        self.widths.remove(index);
+        for row in &mut self.rows {
+            row.remove(index);
+        }
+    }
+
+    /// Lay the table out in `max_width` by truncating the urls, then by
+    /// dropping the columns of the lowest priority if `drop_columns`,
+    /// `None` when the table cannot fit
+    fn fit(&self, max_width: usize, drop_columns: bool) -> Option<Table> {
+        let mut table = self.clone();
+        while !table.shrink(max_width) {
+            if !drop_columns {
+                return None;
+            }
+            let (index, _) = table
+                .columns
+                .iter()
+                .enumerate()
+                .filter_map(|(i, column)| column.priority().map(|priority| (i, priority)))
+                .min_by_key(|(_, priority)| *priority)?;
+            table.remove(index);
+            table.widths = table.natural_widths();
+        }
+        for (i, column) in table.columns.iter().enumerate() {
+            if column.is_truncatable() {
+                for row in &mut table.rows {
+                    row[i].text = truncate_middle(&row[i].text, table.widths[i]);
+                }
+            }
+        }
+        Some(table)
+    }
+
+    /// Narrow the widest truncatable column until the table fits in
+    /// `max_width`, false when they cannot be narrowed enough
+    fn shrink(&mut self, max_width: usize) -> bool {
+        while self.total_width() > max_width {
+            let widest = self
+                .columns
+                .iter()
+                .zip(&self.widths)
+                .enumerate()
+                .filter(|(_, (column, width))| {
+                    column.is_truncatable() && **width > MIN_TRUNCATED_WIDTH
+                })
+                .max_by_key(|(_, (_, width))| **width)
+                .map(|(i, _)| i);
+            match widest {
+                Some(i) => self.widths[i] -= 1,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    fn print(&self, painter: &Painter) {
+        println!(
+            "{}{}{}",
+            painter.paint(Bold),
+            self.format_row(&self.headers, painter),
+            painter.paint(Reset)
+        );
+        for row in &self.rows {
+            println!("{}", self.format_row(row, painter));
+        }
+    }
+
+    fn format_row(&self, cells: &[Cell], painter: &Painter) -> String {
+        // the values of the not found mirrors stop after the url
+        let len = cells
+            .iter()
+            .rposition(|cell| !cell.text.is_empty())
+            .map_or(0, |i| i + 1);
+        let mut line = String::new();
+        for (i, ((column, cell), width)) in
+            self.columns.iter().zip(cells).zip(&self.widths).enumerate()
+        {
+            if i == len {
+                break;
+            }
+            if i > 0 {
+                line.push_str(&" ".repeat(column.gap()));
+            }
+            line.push_str(&cell.style);
+            if column.is_left_aligned() && i + 1 == len {
+                line.push_str(&cell.text);
+            } else if column.is_left_aligned() {
+                line.push_str(&format!("{:<width$}", cell.text));
+            } else {
+                line.push_str(&format!("{:>width$}", cell.text));
+            }
+            if !cell.style.is_empty() {
+                line.push_str(&painter.paint(Reset));
+            }
+        }
+        // the blank marker of a normal value ending the row
+        line.truncate(line.trim_end().len());
+        line
+    }
+
+    /// Print every mirror as a stack of labelled values, for the
+    /// terminals too narrow for the table
+    fn print_cards(&self, max_width: usize, painter: &Painter) {
+        let label_width = self.headers.iter().map(Cell::width).max().unwrap_or(0);
+        let value_width = max_width.saturating_sub(label_width + 1);
+        // too narrow to put the values beside the labels
+        let stacked = value_width < MIN_CARD_VALUE_WIDTH;
+        for (i, row) in self.rows.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            for (header, cell) in self.headers.iter().zip(row) {
+                if cell.text.is_empty() {
+                    continue;
+                }
+                let reset = if cell.style.is_empty() {
+                    "".to_string()
+                } else {
+                    painter.paint(Reset)
+                };
+                let label = format!(
+                    "{}{:>label_width$}{}",
+                    painter.paint(Bold),
+                    header.text,
+                    painter.paint(Reset)
+                );
+                if stacked {
+                    println!(
+                        "{}{}{}",
+                        painter.paint(Bold),
+                        header.text,
+                        painter.paint(Reset)
+                    );
+                    for line in wrap(cell.text.trim_end(), max_width) {
+                        println!("{}{}{}", cell.style, line, reset);
+                    }
+                    continue;
+                }
+                // the values are wrapped, never truncated
+                for (j, line) in wrap(cell.text.trim_end(), value_width).iter().enumerate() {
+                    let label = if j == 0 {
+                        label.clone()
+                    } else {
+                        " ".repeat(label_width)
+                    };
+                    println!("{} {}{}{}", label, cell.style, line, reset);
+                }
+            }
+        }
+    }
+}
+
+/// Cut a text into lines of `width` characters at most, after a space
+/// or a slash when possible
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut rest: Vec<char> = text.chars().collect();
+    let mut lines = vec![];
+    while rest.len() > width {
+        let cut = rest[..width]
+            .iter()
+            .rposition(|c| *c == ' ' || *c == '/')
+            .map_or(width, |i| i + 1);
+        let line: String = rest.drain(..cut).collect();
+        lines.push(line.trim_end().to_string());
+        while rest.first() == Some(&' ') {
+            rest.remove(0);
+        }
+    }
+    lines.push(rest.into_iter().collect());
+    lines
+}
+
+/// Shorten a text to `width` characters by replacing its middle with an
+/// ellipsis, keeping the host and the path of an url readable
+fn truncate_middle(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len <= width {
+        return text.to_string();
+    }
+    let kept = width.saturating_sub(ELLIPSIS.chars().count());
+    let head = kept - kept / 2;
+    let tail = kept / 2;
+    let head: String = text.chars().take(head).collect();
+    let tail: String = text.chars().skip(len - tail).collect();
+    format!("{}{}{}", head, ELLIPSIS, tail)
+}
+
+/// Print the table, laid out in `max_width` if given, usually the width
+/// of the terminal. The columns chosen by the user are not dropped to
+/// fit, see `Table::fit`.
+pub fn print_mirrors(
+    mirrors: &[MirrorState],
+    columns: &[Column],
+    drop_columns: bool,
+    painter: &Painter,
+    thresholds: &Thresholds,
+    max_width: Option<usize>,
+) {
+    let table = Table::new(mirrors, columns, painter, thresholds);
+    match max_width {
+        Some(max_width) => match table.fit(max_width, drop_columns) {
+            Some(table) => table.print(painter),
+            None => table.print_cards(max_width, painter),
+        },
+        None => table.print(painter),
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mirror;
+
+    fn table() -> Table {
+        // the url is 22 characters long, it can lose 2 of them
+        let mirrors = [MirrorState::Synced(mirror(0.5))];
+        Table::new(
+            &mirrors,
+            &COLUMNS,
+            &Painter::new(false),
+            &Thresholds::default(),
+        )
+    }
+
+    #[test]
+    fn truncate_middle_keeps_the_width() {
+        let url = "https://a.example.org/";
+        assert_eq!(truncate_middle(url, 22), url);
+        // the head gets the odd character
+        assert_eq!(truncate_middle(url, 20), "https://a.…mple.org/");
+        assert_eq!(truncate_middle(url, 21), "https://a.…ample.org/");
+        for width in 1..=url.len() {
+            assert_eq!(truncate_middle(url, width).chars().count(), width);
+        }
+    }
+
+    #[test]
+    fn shrink_down_to_the_minimum_width() {
+        let mut table = table();
+        let width = table.total_width();
+        assert!(table.shrink(width - 2));
+        assert_eq!(table.widths[1], MIN_TRUNCATED_WIDTH);
+        assert_eq!(table.total_width(), width - 2);
+        assert!(!table.clone().shrink(width - 3));
+    }
+
+    #[test]
+    fn fit_truncates_the_urls_first() {
+        let table = table();
+        let fitted = table.fit(table.total_width() - 2, true).unwrap();
+        assert_eq!(fitted.columns, COLUMNS);
+        assert_eq!(fitted.rows[0][1].text, "https://a.…mple.org/");
+    }
+
+    #[test]
+    fn fit_drops_the_columns_by_priority() {
+        let table = table();
+        let fitted = table.fit(table.total_width() - 3, true).unwrap();
+        let mut columns = COLUMNS.to_vec();
+        columns.retain(|column| *column != Column::DurationStddev);
+        assert_eq!(fitted.columns, columns);
+        // the url has the room again
+        assert_eq!(fitted.rows[0][1].text, "https://a.example.org/");
+
+        let dropped: Vec<Column> = (1..table.total_width())
+            .rev()
+            .filter_map(|width| table.fit(width, true))
+            .map(|fitted| fitted.columns)
+            .fold(vec![], |mut dropped, columns| {
+                for column in COLUMNS {
+                    if !columns.contains(&column) && !dropped.contains(&column) {
+                        dropped.push(column);
+                    }
+                }
+                dropped
+            });
+        assert_eq!(
+            dropped,
+            [
+                Column::DurationStddev,
+                Column::DurationAvg,
+                Column::Protocol,
+                Column::Completion,
+                Column::Country,
+                Column::Score,
+                Column::Delay
+            ]
+        );
+    }
+
+    #[test]
+    fn fit_keeps_the_state_and_the_url() {
+        let table = table();
+        // "State", a space and the shortest url
+        let fitted = table.fit(MIN_TRUNCATED_WIDTH + 6, true).unwrap();
+        assert_eq!(fitted.columns, [Column::State, Column::Url]);
+        assert!(table.fit(MIN_TRUNCATED_WIDTH + 5, true).is_none());
+    }
+
+    #[test]
+    fn fit_keeps_the_chosen_columns() {
+        let table = table();
+        assert!(table.fit(table.total_width() - 2, false).is_some());
+        assert!(table.fit(table.total_width() - 3, false).is_none());
+    }
+
+    #[test]
+    fn wrap_after_the_slashes() {
+        assert_eq!(
+            wrap("https://b.example.org/arch/", 20),
+            ["https://", "b.example.org/arch/"]
+        );
+        assert_eq!(
+            wrap("https://a.example.org/", 12),
+            ["https://", "a.example.or", "g/"]
+        );
+    }
+
+    #[test]
+    fn wrap_after_the_spaces() {
+        assert_eq!(wrap("Out of sync!", 12), ["Out of sync!"]);
+        assert_eq!(wrap("Out of sync!", 8), ["Out of", "sync!"]);
+    }
+
+    #[test]
+    fn wrap_keeps_every_character() {
+        let url = "https://mirror.example.org/archlinux/";
+        for width in 1..=url.len() {
+            let lines = wrap(url, width);
+            assert!(lines.iter().all(|line| line.chars().count() <= width));
+            assert_eq!(lines.concat(), url);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::error::Error;
+use crate::painter::Level;
+use crate::Mirror;
+
+const COMPLETION_WARNING: f64 = 100.0;
+const COMPLETION_CRITICAL: f64 = 95.0;
+const DELAY_WARNING: u32 = 30;
+const DELAY_CRITICAL: u32 = 60;
+const SCORE_WARNING: f64 = 1.0;
+const SCORE_CRITICAL: f64 = 2.0;
+
+/// Values from which a mirror value is worth a warning or is critical
+#[derive(Debug, Clone, Copy)]
+pub struct Threshold<T> {
+    pub warning: T,
+    pub critical: T,
+}
+
+impl<T: PartialOrd> Threshold<T> {
+    /// Level of a value, the lower the worse
+    fn below(&self, value: T) -> Level {
+        if value < self.critical {
+            Level::Critical
+        } else if value < self.warning {
+            Level::Warning
+        } else {
+            Level::Normal
+        }
+    }
+
+    /// Level of a value, the higher the worse
+    fn above(&self, value: T) -> Level {
+        if value > self.critical {
+            Level::Critical
+        } else if value > self.warning {
+            Level::Warning
+        } else {
+            Level::Normal
+        }
+    }
+}
+
+/// Thresholds of the completion (%), the delay (minutes) and the score
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub completion: Threshold<f64>,
+    pub delay: Threshold<u32>,
+    pub score: Threshold<f64>,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            completion: Threshold {
+                warning: COMPLETION_WARNING,
+                critical: COMPLETION_CRITICAL,
+            },
+            delay: Threshold {
+                warning: DELAY_WARNING,
+                critical: DELAY_CRITICAL,
+            },
+            score: Threshold {
+                warning: SCORE_WARNING,
+                critical: SCORE_CRITICAL,
+            },
+        }
+    }
+}
+
+impl Thresholds {
+    /// Check that every critical threshold is beyond its warning one
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |name: &str, direction: &str| {
+            Err(Error::new(format!(
+                "the critical {} threshold must be {} or equal to the warning one",
+                name, direction
+            )))
+        };
+        if self.completion.critical > self.completion.warning {
+            return invalid("completion", "lower than");
+        }
+        if self.delay.critical < self.delay.warning {
+            return invalid("delay", "greater than");
+        }
+        if self.score.critical < self.score.warning {
+            return invalid("score", "greater than");
+        }
+        Ok(())
+    }
+
+    pub fn completion(&self, mirror: &Mirror) -> Level {
+        mirror
+            .completion
+            .map_or(Level::Normal, |value| self.completion.below(value))
+    }
+
+    /// The delay is in seconds, compared as is to the thresholds in
+    /// minutes, a mirror 1h00m59s behind is over 60 minutes
+    pub fn delay(&self, mirror: &Mirror) -> Level {
+        let seconds = Threshold {
+            warning: self.delay.warning.saturating_mul(60),
+            critical: self.delay.critical.saturating_mul(60),
+        };
+        mirror
+            .delay
+            .map_or(Level::Normal, |value| seconds.above(value))
+    }
+
+    pub fn score(&self, mirror: &Mirror) -> Level {
+        mirror
+            .score
+            .map_or(Level::Normal, |value| self.score.above(value))
+    }
+
+    /// The worst level of the values of the mirror
+    pub fn level(&self, mirror: &Mirror) -> Level {
+        self.completion(mirror)
+            .max(self.delay(mirror))
+            .max(self.score(mirror))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mirror;
+
+    fn completion(value: f64) -> Level {
+        Thresholds::default().completion(&Mirror {
+            completion: Some(value),
+            ..mirror(0.5)
+        })
+    }
+
+    /// `seconds` behind
+    fn delay(seconds: u32) -> Level {
+        Thresholds::default().delay(&Mirror {
+            delay: Some(seconds),
+            ..mirror(0.5)
+        })
+    }
+
+    fn score(value: f64) -> Level {
+        Thresholds::default().score(&mirror(value))
+    }
+
+    #[test]
+    fn completion_boundaries() {
+        assert_eq!(completion(100.0), Level::Normal);
+        assert_eq!(completion(99.9), Level::Warning);
+        assert_eq!(completion(95.0), Level::Warning);
+        assert_eq!(completion(94.9), Level::Critical);
+    }
+
+    #[test]
+    fn delay_boundaries_in_seconds() {
+        assert_eq!(delay(30 * 60), Level::Normal);
+        assert_eq!(delay(30 * 60 + 1), Level::Warning);
+        assert_eq!(delay(60 * 60), Level::Warning);
+        // a second over the hour is critical, not floored to 60 minutes
+        assert_eq!(delay(60 * 60 + 1), Level::Critical);
+        assert_eq!(delay(61 * 60 - 1), Level::Critical);
+        let thresholds = Thresholds {
+            delay: Threshold {
+                warning: u32::MAX,
+                critical: u32::MAX,
+            },
+            ..Thresholds::default()
+        };
+        let mirror = Mirror {
+            delay: Some(u32::MAX - 1),
+            ..mirror(0.5)
+        };
+        assert_eq!(thresholds.delay(&mirror), Level::Normal);
+    }
+
+    #[test]
+    fn score_boundaries() {
+        assert_eq!(score(1.0), Level::Normal);
+        assert_eq!(score(1.1), Level::Warning);
+        assert_eq!(score(2.0), Level::Warning);
+        assert_eq!(score(2.1), Level::Critical);
+    }
+
+    #[test]
+    fn missing_values_are_normal() {
+        let thresholds = Thresholds::default();
+        let mirror = Mirror {
+            completion: None,
+            delay: None,
+            score: None,
+            ..mirror(0.5)
+        };
+        assert_eq!(thresholds.level(&mirror), Level::Normal);
+    }
+
+    #[test]
+    fn level_is_the_worst() {
+        let mirror = Mirror {
+            completion: Some(99.0),
+            ..mirror(3.0)
+        };
+        assert_eq!(Thresholds::default().level(&mirror), Level::Critical);
+    }
+
+    #[test]
+    fn validate() {
+        let defaults = Thresholds::default();
+        assert!(defaults.validate().is_ok());
+        // equal thresholds skip the warning level
+        let equal = Thresholds {
+            completion: Threshold {
+                warning: 90.0,
+                critical: 90.0,
+            },
+            delay: Threshold {
+                warning: 60,
+                critical: 60,
+            },
+            score: Threshold {
+                warning: 2.0,
+                critical: 2.0,
+            },
+        };
+        assert!(equal.validate().is_ok());
+        let invalid = [
+            Thresholds {
+                completion: Threshold {
+                    warning: 90.0,
+                    critical: 95.0,
+                },
+                ..defaults
+            },
+            Thresholds {
+                delay: Threshold {
+                    warning: 60,
+                    critical: 30,
+                },
+                ..defaults
+            },
+            Thresholds {
+                score: Threshold {
+                    warning: 2.0,
+                    critical: 1.0,
+                },
+                ..defaults
+            },
+        ];
+        for thresholds in invalid {
+            assert!(thresholds.validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,178 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+/// Current Unix time, in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// Parse an ISO 8601 UTC date time as sent by the mirror status API,
+/// e.g. `2023-06-20T10:01:23Z` or `2023-06-20T10:01:23.123456Z`, into a
+/// Unix time in seconds
+pub fn parse_iso8601(value: &str) -> Option<u64> {
+    let value = value.strip_suffix('Z').unwrap_or(value);
+    let (date, time) = value.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<u64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    // fractional seconds are dropped
+    let time = time.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hours, minutes, seconds) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if year < 1970
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hours >= 24
+        || minutes >= 60
+        || seconds >= 60
+    {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * DAY + hours * HOUR + minutes * MINUTE + seconds)
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Number of days since 1970-01-01 of a date of the proleptic Gregorian
+/// calendar, see http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month = (month + 9) % 12;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146097 + day_of_era).saturating_sub(719468)
+}
+
+/// Date of a number of days since 1970-01-01, the inverse of
+/// `days_from_civil`
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Format a Unix time in seconds as an ISO 8601 UTC date time, e.g.
+/// `2023-06-20T10:01:23Z`
+pub fn format_iso8601(time: u64) -> String {
+    let (year, month, day) = civil_from_days(time / DAY);
+    let seconds = time % DAY;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / HOUR,
+        seconds % HOUR / MINUTE,
+        seconds % MINUTE
+    )
+}
+
+/// Format a Unix time in seconds for a file name, e.g. `20230620T100123`
+pub fn format_stamp(time: u64) -> String {
+    format_iso8601(time).replace(['-', ':', 'Z'], "")
+}
+
+/// Format a duration in seconds with its largest unit, e.g. `3h`
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        s if s < MINUTE => format!("{}s", s),
+        s if s < HOUR => format!("{}m", s / MINUTE),
+        s if s < DAY => format!("{}h", s / HOUR),
+        s => format!("{}d", s / DAY),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epoch() {
+        assert_eq!(parse_iso8601("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(format_iso8601(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn round_trips() {
+        for value in [
+            "2023-06-20T10:01:23Z",
+            "2024-02-29T12:00:00Z",
+            "2000-02-29T00:00:00Z",
+            "2023-12-31T23:59:59Z",
+            "2024-01-01T00:00:00Z",
+            "2100-03-01T00:00:00Z",
+        ] {
+            let time = parse_iso8601(value).unwrap();
+            assert_eq!(format_iso8601(time), value);
+        }
+    }
+
+    #[test]
+    fn leap_days() {
+        let leap_day = parse_iso8601("2024-02-29T00:00:00Z").unwrap();
+        assert_eq!(format_iso8601(leap_day + DAY), "2024-03-01T00:00:00Z");
+        assert_eq!(parse_iso8601("2023-02-29T00:00:00Z"), None);
+        assert_eq!(parse_iso8601("2100-02-29T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn year_boundary() {
+        let last_second = parse_iso8601("2023-12-31T23:59:59Z").unwrap();
+        assert_eq!(format_iso8601(last_second + 1), "2024-01-01T00:00:00Z");
+        assert_eq!(format_stamp(last_second + 1), "20240101T000000");
+    }
+
+    #[test]
+    fn fractional_seconds_and_missing_z() {
+        let time = parse_iso8601("2023-06-20T10:01:23Z");
+        assert_eq!(parse_iso8601("2023-06-20T10:01:23.123456Z"), time);
+        assert_eq!(parse_iso8601("2023-06-20T10:01:23"), time);
+        assert_eq!(parse_iso8601("2023-06-20T10:01:23.5"), time);
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert_eq!(parse_iso8601("2023-06-20T25:99:99Z"), None);
+        assert_eq!(parse_iso8601("2023-06-20T24:00:00Z"), None);
+        assert_eq!(parse_iso8601("2023-06-20T10:60:00Z"), None);
+        assert_eq!(parse_iso8601("2023-13-01T00:00:00Z"), None);
+        assert_eq!(parse_iso8601("2023-04-31T00:00:00Z"), None);
+        assert_eq!(parse_iso8601("1969-12-31T23:59:59Z"), None);
+        assert_eq!(parse_iso8601("2023-06-20"), None);
+        assert_eq!(parse_iso8601(""), None);
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(format_age(59), "59s");
+        assert_eq!(format_age(HOUR), "1h");
+        assert_eq!(format_age(3 * DAY + HOUR), "3d");
+    }
+}
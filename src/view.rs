@@ -0,0 +1,299 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::cli::{Sort, StateFilter};
+use crate::{MirrorState, Mirrorlist};
+use std::cmp::Ordering;
+
+/// Which mirrors are printed, and in which order
+#[derive(Debug, Clone)]
+pub struct View {
+    pub sort: Sort,
+    /// Printed states, all when empty
+    pub states: Vec<StateFilter>,
+    /// Country codes or names, all when empty
+    pub countries: Vec<String>,
+    /// All when empty
+    pub protocols: Vec<String>,
+}
+
+impl View {
+    /// The mirrorlists with their mirrors filtered and sorted, the
+    /// suggestions and diagnoses of the mirrors filtered out are dropped
+    pub fn apply(&self, lists: &[Mirrorlist]) -> Vec<Mirrorlist> {
+        lists
+            .iter()
+            .map(|list| {
+                let mirrors = self.apply_mirrors(&list.mirrors);
+                let kept = |url: &str| mirrors.iter().any(|mirror| mirror.url() == url);
+                Mirrorlist {
+                    path: list.path.clone(),
+                    repository: list.repository.clone(),
+                    disabled: self.apply_mirrors(&list.disabled),
+                    suggestions: list
+                        .suggestions
+                        .iter()
+                        .filter(|suggestion| kept(&suggestion.url))
+                        .cloned()
+                        .collect(),
+                    diagnoses: list
+                        .diagnoses
+                        .iter()
+                        .filter(|diagnosis| kept(&diagnosis.url))
+                        .cloned()
+                        .collect(),
+                    mirrors,
+                    unofficial: list.unofficial,
+                }
+            })
+            .collect()
+    }
+
+    fn apply_mirrors(&self, mirrors: &[MirrorState]) -> Vec<MirrorState> {
+        let mut mirrors: Vec<MirrorState> = mirrors
+            .iter()
+            .filter(|mirror| self.matches(mirror))
+            .cloned()
+            .collect();
+        // stable, the mirrorlist order breaks the ties
+        mirrors.sort_by(|a, b| self.compare(a, b));
+        mirrors
+    }
+
+    fn matches(&self, state: &MirrorState) -> bool {
+        let state_matches = self.states.is_empty()
+            || self.states.iter().any(|filter| {
+                matches!(
+                    (filter, state),
+                    (StateFilter::Synced, MirrorState::Synced(_))
+                        | (StateFilter::OutOfSync, MirrorState::OutOfSync(_))
+                        | (StateFilter::NotFound, MirrorState::NotFound(_))
+                )
+            });
+        // the country of a mirror missing from the status list is unknown
+        let country_matches = self.countries.is_empty()
+            || state.mirror().is_some_and(|mirror| {
+                self.countries.iter().any(|country| {
+                    country.eq_ignore_ascii_case(&mirror.country_code)
+                        || country.eq_ignore_ascii_case(&mirror.country)
+                })
+            });
+        let protocol_matches = self.protocols.is_empty()
+            || self
+                .protocols
+                .iter()
+                .any(|protocol| protocol.eq_ignore_ascii_case(protocol_of(state)));
+        state_matches && country_matches && protocol_matches
+    }
+
+    /// Worst first, the mirrors missing a value last
+    fn compare(&self, a: &MirrorState, b: &MirrorState) -> Ordering {
+        let (a_mirror, b_mirror) = (a.mirror(), b.mirror());
+        match self.sort {
+            Sort::Order => Ordering::Equal,
+            Sort::State => severity(b).cmp(&severity(a)),
+            Sort::Score => descending(
+                a_mirror.and_then(|m| m.score),
+                b_mirror.and_then(|m| m.score),
+            ),
+            Sort::Delay => descending(
+                a_mirror.and_then(|m| m.delay),
+                b_mirror.and_then(|m| m.delay),
+            ),
+            Sort::Completion => descending(
+                a_mirror.and_then(|m| m.completion).map(|value| -value),
+                b_mirror.and_then(|m| m.completion).map(|value| -value),
+            ),
+            Sort::Country => match (a_mirror, b_mirror) {
+                (Some(a), Some(b)) => a.country.cmp(&b.country),
+                (a, b) => a.is_none().cmp(&b.is_none()),
+            },
+        }
+    }
+}
+
+/// The protocol of the mirror, or the scheme of the url of a mirror
+/// missing from the status list
+fn protocol_of(state: &MirrorState) -> &str {
+    match state {
+        MirrorState::Synced(mirror) | MirrorState::OutOfSync(mirror) => &mirror.protocol,
+        MirrorState::NotFound(server) => server.split_once("://").map_or("", |(scheme, _)| scheme),
+    }
+}
+
+fn severity(state: &MirrorState) -> u8 {
+    match state {
+        MirrorState::Synced(_) => 0,
+        MirrorState::NotFound(_) => 1,
+        MirrorState::OutOfSync(_) => 2,
+    }
+}
+
+/// Greatest first, `None` last
+fn descending<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mirror;
+    use crate::Mirror;
+
+    fn view(sort: Sort) -> View {
+        View {
+            sort,
+            states: vec![],
+            countries: vec![],
+            protocols: vec![],
+        }
+    }
+
+    fn synced(url: &str, mirror: Mirror) -> MirrorState {
+        MirrorState::Synced(Mirror {
+            url: url.to_string(),
+            ..mirror
+        })
+    }
+
+    fn urls(mirrors: &[MirrorState]) -> Vec<&str> {
+        mirrors.iter().map(MirrorState::url).collect()
+    }
+
+    #[test]
+    fn sort_missing_values_last() {
+        let mirrors = [
+            synced(
+                "https://a/",
+                Mirror {
+                    score: None,
+                    delay: None,
+                    ..mirror(0.0)
+                },
+            ),
+            synced("https://b/", mirror(1.0)),
+            MirrorState::NotFound("https://c/".to_string()),
+            synced(
+                "https://d/",
+                Mirror {
+                    delay: Some(1200),
+                    ..mirror(3.0)
+                },
+            ),
+        ];
+        assert_eq!(
+            urls(&view(Sort::Score).apply_mirrors(&mirrors)),
+            ["https://d/", "https://b/", "https://a/", "https://c/"]
+        );
+        assert_eq!(
+            urls(&view(Sort::Delay).apply_mirrors(&mirrors)),
+            ["https://d/", "https://b/", "https://a/", "https://c/"]
+        );
+    }
+
+    #[test]
+    fn sort_lowest_completion_first() {
+        let completion = |value: Option<f64>| Mirror {
+            completion: value,
+            ..mirror(0.5)
+        };
+        let mirrors = [
+            synced("https://a/", completion(Some(100.0))),
+            synced("https://b/", completion(None)),
+            synced("https://c/", completion(Some(90.0))),
+        ];
+        assert_eq!(
+            urls(&view(Sort::Completion).apply_mirrors(&mirrors)),
+            ["https://c/", "https://a/", "https://b/"]
+        );
+    }
+
+    #[test]
+    fn sort_keeps_the_order_of_ties() {
+        let mirrors = [
+            synced("https://a/", mirror(0.5)),
+            MirrorState::NotFound("https://b/".to_string()),
+            MirrorState::OutOfSync(Mirror {
+                url: "https://c/".to_string(),
+                ..mirror(0.5)
+            }),
+            synced("https://d/", mirror(0.5)),
+            MirrorState::NotFound("https://e/".to_string()),
+        ];
+        assert_eq!(
+            urls(&view(Sort::State).apply_mirrors(&mirrors)),
+            [
+                "https://c/",
+                "https://b/",
+                "https://e/",
+                "https://a/",
+                "https://d/"
+            ]
+        );
+        assert_eq!(
+            urls(&view(Sort::Score).apply_mirrors(&mirrors)),
+            [
+                "https://a/",
+                "https://c/",
+                "https://d/",
+                "https://b/",
+                "https://e/"
+            ]
+        );
+        assert_eq!(
+            urls(&view(Sort::Order).apply_mirrors(&mirrors)),
+            urls(&mirrors)
+        );
+    }
+
+    #[test]
+    fn filter_by_country_code_or_name() {
+        let german = synced(
+            "https://a/",
+            Mirror {
+                country: "Germany".to_string(),
+                country_code: "DE".to_string(),
+                ..mirror(0.5)
+            },
+        );
+        let french = synced("https://b/", mirror(0.5));
+        let unknown = MirrorState::NotFound("https://c/".to_string());
+        let mirrors = [german, french, unknown];
+        let countries = |countries: &[&str]| View {
+            countries: countries
+                .iter()
+                .map(|country| country.to_string())
+                .collect(),
+            ..view(Sort::Order)
+        };
+        assert_eq!(
+            urls(&countries(&["de"]).apply_mirrors(&mirrors)),
+            ["https://a/"]
+        );
+        assert_eq!(
+            urls(&countries(&["GERMANY", "fr"]).apply_mirrors(&mirrors)),
+            ["https://a/", "https://b/"]
+        );
+        assert_eq!(urls(&countries(&[]).apply_mirrors(&mirrors)).len(), 3);
+    }
+
+    #[test]
+    fn filter_by_state_and_protocol() {
+        let mirrors = [
+            synced("https://a/", mirror(0.5)),
+            MirrorState::NotFound("http://b/".to_string()),
+            MirrorState::NotFound("https://c/".to_string()),
+        ];
+        let filtered = View {
+            states: vec![StateFilter::NotFound],
+            protocols: vec!["HTTP".to_string()],
+            ..view(Sort::Order)
+        }
+        .apply_mirrors(&mirrors);
+        assert_eq!(urls(&filtered), ["http://b/"]);
+    }
+}